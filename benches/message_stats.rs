@@ -0,0 +1,83 @@
+//! Compares user statistics served from `daily_activity` with counting raw `messages` rows
+//!
+//! `cargo bench --bench message_stats`, set `MESSAGES` to change the number of seeded rows.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use dog_bot_template::{config::DatabaseCfg, database::BotDatabase};
+use entities::messages::Model as MessageRecord;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serenity::all::GuildId;
+
+const DAYS: i64 = 365;
+const RUNS: usize = 5;
+
+fn median(mut samples: Vec<Duration>) -> Duration {
+    samples.sort();
+    samples[samples.len() / 2]
+}
+
+#[tokio::main]
+async fn main() {
+    let count = std::env::var("MESSAGES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1_000_000);
+    let dir = std::env::temp_dir().join(format!("dog-bot-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = BotDatabase::new(dir.join("bench.db"), &DatabaseCfg::default())
+        .await
+        .unwrap();
+    db.migrate().await.unwrap();
+
+    let mut rng = StdRng::seed_from_u64(42);
+    let now = Utc::now();
+    let start = Instant::now();
+    for batch in (0..count).collect::<Vec<i64>>().chunks(10_000) {
+        let messages = batch
+            .iter()
+            .map(|&id| MessageRecord {
+                message_id: id + 1,
+                user_id: rng.random_range(1..=100),
+                guild_id: 1,
+                channel_id: rng.random_range(1..=10),
+                timestamp: (now - chrono::Duration::seconds(rng.random_range(0..DAYS * 86_400)))
+                    .into(),
+                edit_count: 0,
+                edited_at: None,
+                attachments: rng.random_range(0..=1),
+                parent_id: None,
+            })
+            .collect::<Vec<_>>();
+        db.message().record_many(&messages).await.unwrap();
+    }
+    println!("seeded {count} messages in {:?}", start.elapsed());
+
+    let repo = db.message();
+    let guild_id = GuildId::new(1);
+    let ago = |days: i64| {
+        Some(DateTime::<FixedOffset>::from(
+            now - chrono::Duration::days(days),
+        ))
+    };
+    println!("{:<8} {:>12} {:>12}", "window", "raw", "rollup");
+    for (name, from) in [("7d", ago(7)), ("30d", ago(30)), ("all", None)] {
+        let to = None::<DateTime<FixedOffset>>;
+        let (mut raw, mut rollup) = (Vec::new(), Vec::new());
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let expected = repo
+                .get_user_stats_raw(guild_id, None, from, to)
+                .await
+                .unwrap();
+            raw.push(start.elapsed());
+            let start = Instant::now();
+            let actual = repo.get_user_stats(guild_id, None, from, to).await.unwrap();
+            rollup.push(start.elapsed());
+            assert_eq!(actual, expected);
+        }
+        println!("{name:<8} {:>12?} {:>12?}", median(raw), median(rollup));
+    }
+    std::fs::remove_dir_all(dir).ok();
+}
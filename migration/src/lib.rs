@@ -1,12 +1,30 @@
 pub use sea_orm_migration::prelude::*;
 
-mod m20220101_000001_create_table;
+mod m20261019_000001_create_system_samples;
+mod m20261019_000002_create_command_usage;
+mod m20261019_000003_create_timezones;
+mod m20261019_000004_create_messages;
+mod m20261019_000005_create_daily_activity;
+mod m20261019_000006_track_message_activity;
+mod m20261019_000007_create_mod_cases;
+mod m20261019_000008_create_automod_rules;
+mod m20261019_000009_create_lockdowns;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20261019_000001_create_system_samples::Migration),
+            Box::new(m20261019_000002_create_command_usage::Migration),
+            Box::new(m20261019_000003_create_timezones::Migration),
+            Box::new(m20261019_000004_create_messages::Migration),
+            Box::new(m20261019_000005_create_daily_activity::Migration),
+            Box::new(m20261019_000006_track_message_activity::Migration),
+            Box::new(m20261019_000007_create_mod_cases::Migration),
+            Box::new(m20261019_000008_create_automod_rules::Migration),
+            Box::new(m20261019_000009_create_lockdowns::Migration),
+        ]
     }
 }
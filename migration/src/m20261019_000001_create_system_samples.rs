@@ -0,0 +1,60 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SystemSamples::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SystemSamples::Id))
+                    .col(timestamp_with_time_zone(SystemSamples::SampledAt))
+                    .col(double(SystemSamples::CpuUsage))
+                    .col(big_integer(SystemSamples::UsedMemory))
+                    .col(big_integer(SystemSamples::TotalMemory))
+                    .col(big_integer(SystemSamples::Allocated))
+                    .col(big_integer(SystemSamples::Resident))
+                    .col(big_integer_null(SystemSamples::GatewayLatencyMs))
+                    .col(big_integer(SystemSamples::DbLatencyUs))
+                    .col(big_integer(SystemSamples::AliveTasks))
+                    .col(big_integer(SystemSamples::QueueDepth))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_system_samples_sampled_at")
+                    .table(SystemSamples::Table)
+                    .col(SystemSamples::SampledAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SystemSamples::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SystemSamples {
+    Table,
+    Id,
+    SampledAt,
+    CpuUsage,
+    UsedMemory,
+    TotalMemory,
+    Allocated,
+    Resident,
+    GatewayLatencyMs,
+    DbLatencyUs,
+    AliveTasks,
+    QueueDepth,
+}
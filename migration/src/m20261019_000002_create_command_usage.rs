@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CommandUsage::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CommandUsage::Id))
+                    .col(string(CommandUsage::Command))
+                    .col(big_integer_null(CommandUsage::GuildId))
+                    .col(big_integer(CommandUsage::ChannelId))
+                    .col(big_integer(CommandUsage::UserId))
+                    .col(timestamp_with_time_zone(CommandUsage::InvokedAt))
+                    .col(big_integer(CommandUsage::DurationMs))
+                    .col(string_null(CommandUsage::ErrorKind))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_command_usage_invoked_at")
+                    .table(CommandUsage::Table)
+                    .col(CommandUsage::InvokedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CommandUsage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CommandUsage {
+    Table,
+    Id,
+    Command,
+    GuildId,
+    ChannelId,
+    UserId,
+    InvokedAt,
+    DurationMs,
+    ErrorKind,
+}
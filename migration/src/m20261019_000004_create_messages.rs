@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Messages::Table)
+                    .if_not_exists()
+                    .col(big_integer(Messages::MessageId).primary_key())
+                    .col(big_integer(Messages::UserId))
+                    .col(big_integer(Messages::GuildId))
+                    .col(big_integer(Messages::ChannelId))
+                    .col(timestamp_with_time_zone(Messages::Timestamp))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_messages_guild_timestamp")
+                    .table(Messages::Table)
+                    .col(Messages::GuildId)
+                    .col(Messages::Timestamp)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_messages_user_guild")
+                    .table(Messages::Table)
+                    .col(Messages::UserId)
+                    .col(Messages::GuildId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Messages::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Messages {
+    Table,
+    MessageId,
+    UserId,
+    GuildId,
+    ChannelId,
+    Timestamp,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyActivity::Table)
+                    .if_not_exists()
+                    .col(big_integer(DailyActivity::GuildId))
+                    .col(big_integer(DailyActivity::ChannelId))
+                    .col(big_integer(DailyActivity::UserId))
+                    .col(date(DailyActivity::Day))
+                    .col(big_integer(DailyActivity::Count))
+                    .primary_key(
+                        Index::create()
+                            .col(DailyActivity::GuildId)
+                            .col(DailyActivity::ChannelId)
+                            .col(DailyActivity::UserId)
+                            .col(DailyActivity::Day),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_daily_activity_guild_day")
+                    .table(DailyActivity::Table)
+                    .col(DailyActivity::GuildId)
+                    .col(DailyActivity::Day)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Roll up messages recorded before this table existed
+        let day = match manager.get_database_backend() {
+            DbBackend::Postgres => "(timestamp AT TIME ZONE 'UTC')::date",
+            _ => "date(timestamp)",
+        };
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                "INSERT INTO daily_activity (guild_id, channel_id, user_id, day, count) \
+                 SELECT guild_id, channel_id, user_id, {day}, COUNT(*) FROM messages \
+                 GROUP BY guild_id, channel_id, user_id, {day}"
+            ))
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DailyActivity::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DailyActivity {
+    Table,
+    GuildId,
+    ChannelId,
+    UserId,
+    Day,
+    Count,
+}
@@ -0,0 +1,123 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only accepts one change per `ALTER TABLE`
+        for column in [
+            big_integer(Messages::EditCount).default(0).to_owned(),
+            timestamp_with_time_zone_null(Messages::EditedAt),
+            big_integer(Messages::Attachments).default(0).to_owned(),
+            big_integer_null(Messages::ParentId),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DailyActivity::Table)
+                    .add_column(big_integer(DailyActivity::Attachments).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reactions::Table)
+                    .if_not_exists()
+                    .col(big_integer(Reactions::MessageId))
+                    .col(big_integer(Reactions::UserId))
+                    .col(string(Reactions::Emoji))
+                    .col(big_integer_null(Reactions::AuthorId))
+                    .col(big_integer(Reactions::GuildId))
+                    .col(big_integer(Reactions::ChannelId))
+                    .col(timestamp_with_time_zone(Reactions::ReactedAt))
+                    .primary_key(
+                        Index::create()
+                            .col(Reactions::MessageId)
+                            .col(Reactions::UserId)
+                            .col(Reactions::Emoji),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_reactions_guild_reacted_at")
+                    .table(Reactions::Table)
+                    .col(Reactions::GuildId)
+                    .col(Reactions::ReactedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reactions::Table).to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DailyActivity::Table)
+                    .drop_column(DailyActivity::Attachments)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [
+            Messages::EditCount,
+            Messages::EditedAt,
+            Messages::Attachments,
+            Messages::ParentId,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Messages::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Messages {
+    Table,
+    EditCount,
+    EditedAt,
+    Attachments,
+    ParentId,
+}
+
+#[derive(DeriveIden)]
+enum DailyActivity {
+    Table,
+    Attachments,
+}
+
+#[derive(DeriveIden)]
+enum Reactions {
+    Table,
+    MessageId,
+    UserId,
+    Emoji,
+    AuthorId,
+    GuildId,
+    ChannelId,
+    ReactedAt,
+}
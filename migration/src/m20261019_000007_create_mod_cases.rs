@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ModCases::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ModCases::Id))
+                    .col(big_integer(ModCases::GuildId))
+                    .col(big_integer(ModCases::CaseNumber))
+                    .col(string(ModCases::Action))
+                    .col(big_integer(ModCases::UserId))
+                    .col(big_integer(ModCases::ModeratorId))
+                    .col(string_null(ModCases::Reason))
+                    .col(big_integer_null(ModCases::DurationSecs))
+                    .col(timestamp_with_time_zone(ModCases::CreatedAt))
+                    .col(timestamp_with_time_zone_null(ModCases::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        // Case numbers count up per guild
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mod_cases_guild_case_number")
+                    .table(ModCases::Table)
+                    .col(ModCases::GuildId)
+                    .col(ModCases::CaseNumber)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mod_cases_guild_user")
+                    .table(ModCases::Table)
+                    .col(ModCases::GuildId)
+                    .col(ModCases::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ModCases::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ModCases {
+    Table,
+    Id,
+    GuildId,
+    CaseNumber,
+    Action,
+    UserId,
+    ModeratorId,
+    Reason,
+    DurationSecs,
+    CreatedAt,
+    UpdatedAt,
+}
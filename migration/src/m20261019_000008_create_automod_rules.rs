@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AutomodRules::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AutomodRules::Id))
+                    .col(big_integer(AutomodRules::GuildId))
+                    .col(string(AutomodRules::Kind))
+                    .col(text_null(AutomodRules::Pattern))
+                    .col(big_integer_null(AutomodRules::Threshold))
+                    .col(big_integer_null(AutomodRules::WindowSecs))
+                    .col(string(AutomodRules::Action))
+                    .col(big_integer_null(AutomodRules::TimeoutSecs))
+                    .col(big_integer_null(AutomodRules::ToiletId))
+                    .col(boolean(AutomodRules::Enabled).default(true))
+                    .col(big_integer(AutomodRules::CreatedBy))
+                    .col(timestamp_with_time_zone(AutomodRules::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_automod_rules_guild")
+                    .table(AutomodRules::Table)
+                    .col(AutomodRules::GuildId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AutomodRules::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AutomodRules {
+    Table,
+    Id,
+    GuildId,
+    Kind,
+    Pattern,
+    Threshold,
+    WindowSecs,
+    Action,
+    TimeoutSecs,
+    ToiletId,
+    Enabled,
+    CreatedBy,
+    CreatedAt,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Lockdowns::Table)
+                    .if_not_exists()
+                    .col(big_integer(Lockdowns::GuildId).primary_key())
+                    .col(timestamp_with_time_zone(Lockdowns::StartedAt))
+                    .col(big_integer_null(Lockdowns::StartedBy))
+                    .col(integer_null(Lockdowns::Verification))
+                    .col(boolean(Lockdowns::InvitesPaused))
+                    .col(text(Lockdowns::Slowmodes))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Lockdowns::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Lockdowns {
+    Table,
+    GuildId,
+    StartedAt,
+    StartedBy,
+    Verification,
+    InvitesPaused,
+    Slowmodes,
+}
@@ -0,0 +1,129 @@
+//! Offline subcommands that don't need a gateway connection
+
+use std::path::Path;
+
+use serenity::{
+    all::{Command, GuildId},
+    http::Http,
+};
+use snafu::ResultExt;
+
+use crate::{
+    DbCommand, commands,
+    config::BotCfg,
+    database::{self, BotDatabase},
+    error::BotError,
+};
+
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Report every invalid field instead of stopping at the first one
+pub fn check_config(path: &Path) -> Result<bool, BotError> {
+    let errors = BotCfg::load(path)?.validate();
+    for error in &errors {
+        eprintln!("{}: {}", path.display(), error);
+    }
+    if errors.is_empty() {
+        println!("{}: OK", path.display());
+    }
+    Ok(errors.is_empty())
+}
+
+pub fn print_schema() -> Result<(), BotError> {
+    println!("{}", BotCfg::schema()?);
+    Ok(())
+}
+
+pub async fn migrate(db: &BotDatabase) -> Result<(), BotError> {
+    db.migrate().await?;
+    println!("Migrations applied");
+    Ok(())
+}
+
+/// Overwrite the application commands globally or in one guild
+pub async fn register_commands(cfg: &BotCfg, guild: Option<GuildId>) -> Result<(), BotError> {
+    let http = Http::new(cfg.token.expose());
+    let app = http.get_current_application_info().await?;
+    http.set_application_id(app.id);
+
+    let commands = poise::builtins::create_application_commands(&commands::commands());
+    let registered = match guild {
+        Some(guild) => guild.set_commands(&http, commands).await?,
+        None => Command::set_global_commands(&http, commands).await?,
+    };
+    let names = registered
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    match guild {
+        Some(guild) => println!(
+            "Registered {} commands in {guild}: {names}",
+            registered.len()
+        ),
+        None => println!("Registered {} global commands: {names}", registered.len()),
+    }
+    Ok(())
+}
+
+pub async fn restore(db: &Path, backup: &Path) -> Result<(), BotError> {
+    let previous = database::backup::restore(db, backup).await?;
+    println!(
+        "Restored {} from {}, the previous file is at {}",
+        db.display(),
+        backup.display(),
+        previous.display()
+    );
+    Ok(())
+}
+
+pub async fn db(db: &BotDatabase, cfg: &Path, command: DbCommand) -> Result<(), BotError> {
+    match command {
+        DbCommand::Backup => {
+            let cfg = BotCfg::load(cfg)
+                .unwrap_or_default()
+                .backup
+                .unwrap_or_default();
+            let backup = db.backup(&cfg).await?;
+            println!(
+                "Backed up to {} ({:.2} MB), integrity ok",
+                backup.path.display(),
+                backup.size as f64 / MB
+            );
+        }
+        DbCommand::Stats => {
+            println!("size: {:.2} MB", db.size().await? as f64 / MB);
+            for table in db.table_stats().await? {
+                println!("{:<24} {:>10}", table.name, table.rows);
+            }
+        }
+        DbCommand::Export { path } => {
+            let export = db.export().await?;
+            let json = serenity::json::to_string_pretty(&export)
+                .whatever_context::<&str, BotError>("Failed to serialize export")?;
+            std::fs::write(&path, json)?;
+            println!("Exported to {}", path.display());
+        }
+        DbCommand::Import { path, replace } => {
+            let json = std::fs::read_to_string(&path)?;
+            let data = serenity::json::from_str(&json)
+                .with_whatever_context::<_, String, BotError>(|_| {
+                    format!("{} is not a valid export", path.display())
+                })?;
+            db.migrate().await?;
+            let rows = db.import(&data, replace).await?;
+            println!("Imported {rows} rows from {}", path.display());
+        }
+        DbCommand::Vacuum => {
+            let before = db.size().await?;
+            db.vacuum().await?;
+            let after = db.size().await?;
+            println!(
+                "Vacuumed: {:.2} MB -> {:.2} MB",
+                before as f64 / MB,
+                after as f64 / MB
+            );
+        }
+    }
+    Ok(())
+}
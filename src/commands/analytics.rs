@@ -0,0 +1,93 @@
+use std::fmt::Write as _;
+
+use chrono::Utc;
+use poise::{ChoiceParameter, CreateReply, command};
+use serenity::all::{colours::branding::BLURPLE, *};
+
+use super::Context;
+use crate::error::BotError;
+
+#[derive(ChoiceParameter, Clone, Copy, Debug)]
+pub enum Period {
+    #[name = "24h"]
+    Day,
+    #[name = "7d"]
+    Week,
+    #[name = "30d"]
+    Month,
+    #[name = "all"]
+    All,
+}
+
+impl Period {
+    /// Length in days, `None` for all time
+    pub fn days(self) -> Option<i64> {
+        match self {
+            Period::Day => Some(1),
+            Period::Week => Some(7),
+            Period::Month => Some(30),
+            Period::All => None,
+        }
+    }
+
+    pub fn duration(self) -> Option<chrono::Duration> {
+        self.days().map(chrono::Duration::days)
+    }
+}
+
+#[command(
+    slash_command,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    name_localized("zh-CN", "命令统计"),
+    description_localized("zh-CN", "查看命令调用次数、错误率和延迟"),
+    ephemeral
+)]
+/// Shows command usage, error rates and latencies
+pub async fn analytics(
+    ctx: Context<'_>,
+    #[description = "Time window, defaults to 7d"] period: Option<Period>,
+) -> Result<(), BotError> {
+    let period = period.unwrap_or(Period::Week);
+    let from = period.duration().map(|d| Utc::now() - d);
+    let stats = ctx.data().db.usage().stats(from).await?;
+
+    if stats.is_empty() {
+        ctx.say("该时间段内没有命令调用记录。").await?;
+        return Ok(());
+    }
+
+    let calls = stats.iter().map(|s| s.calls).sum::<u64>();
+    let errors = stats.iter().map(|s| s.errors).sum::<u64>();
+    let mut table = format!(
+        "{:<20} {:>6} {:>6} {:>8} {:>8}\n",
+        "command", "calls", "err%", "p50", "p95"
+    );
+    for s in stats.iter().take(20) {
+        writeln!(
+            table,
+            "{:<20} {:>6} {:>5.1}% {:>6}ms {:>6}ms",
+            s.command,
+            s.calls,
+            s.errors as f64 / s.calls as f64 * 100.0,
+            s.p50.as_millis(),
+            s.p95.as_millis(),
+        )
+        .ok();
+    }
+
+    let embed = CreateEmbed::new()
+        .title(format!("📊 命令统计 ({})", period.name()))
+        .color(BLURPLE)
+        .field("总调用", calls.to_string(), true)
+        .field("总错误", errors.to_string(), true)
+        .field(
+            "错误率",
+            format!("{:.1}%", errors as f64 / calls as f64 * 100.0),
+            true,
+        )
+        .description(format!("```\n{table}```"))
+        .timestamp(Timestamp::now());
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
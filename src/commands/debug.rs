@@ -0,0 +1,233 @@
+use std::fmt::Write as _;
+
+use poise::{CreateReply, command};
+use serenity::all::{colours::branding::BLURPLE, *};
+
+use super::Context;
+use crate::error::BotError;
+
+const MB: f64 = 1024.0 * 1024.0;
+/// Arena rows shown before the rest are summarized, keeps the table under the embed limit
+const SHOWN_ARENAS: usize = 40;
+
+#[command(
+    slash_command,
+    subcommands("memory", "runtime"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    ephemeral
+)]
+/// Low level diagnostics for the bot owners
+pub async fn debug(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+/// Per-arena figures read through raw mallctl names
+struct ArenaStats {
+    index: u32,
+    threads: u32,
+    active_pages: usize,
+    dirty_pages: usize,
+    resident: usize,
+}
+
+fn arena_stats(index: u32) -> Option<ArenaStats> {
+    use tikv_jemalloc_ctl::raw;
+    let read = |stat: &str| -> Option<usize> {
+        let name = format!("stats.arenas.{index}.{stat}\0");
+        // SAFETY: the queried stats are all `size_t`
+        unsafe { raw::read::<usize>(name.as_bytes()) }.ok()
+    };
+    let threads = {
+        let name = format!("stats.arenas.{index}.nthreads\0");
+        // SAFETY: `nthreads` is an `unsigned`
+        unsafe { raw::read::<u32>(name.as_bytes()) }.ok()?
+    };
+    Some(ArenaStats {
+        index,
+        threads,
+        active_pages: read("pactive")?,
+        dirty_pages: read("pdirty")?,
+        resident: read("resident")?,
+    })
+}
+
+impl ArenaStats {
+    /// Never used by a thread and holding no pages
+    fn is_idle(&self) -> bool {
+        self.threads == 0 && self.active_pages == 0 && self.dirty_pages == 0
+    }
+}
+
+fn mb(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / MB)
+}
+
+/// Table of the arenas in use, at most [`SHOWN_ARENAS`] rows
+fn arena_table(arenas: impl IntoIterator<Item = ArenaStats>, page_size: usize) -> String {
+    let mut table = format!(
+        "{:>5} {:>7} {:>10} {:>10} {:>10}\n",
+        "arena", "threads", "active", "dirty", "resident"
+    );
+    let (mut shown, mut hidden, mut idle) = (0, 0, 0);
+    for arena in arenas {
+        if arena.is_idle() {
+            idle += 1;
+        } else if shown == SHOWN_ARENAS {
+            hidden += 1;
+        } else {
+            shown += 1;
+            writeln!(
+                table,
+                "{:>5} {:>7} {:>10} {:>10} {:>10}",
+                arena.index,
+                arena.threads,
+                mb(arena.active_pages * page_size),
+                mb(arena.dirty_pages * page_size),
+                mb(arena.resident),
+            )
+            .ok();
+        }
+    }
+    if hidden > 0 {
+        writeln!(table, "… {hidden} more arenas").ok();
+    }
+    if idle > 0 {
+        writeln!(table, "{idle} idle arenas not shown").ok();
+    }
+    table
+}
+
+#[cfg(feature = "profiling")]
+fn dump_heap_profile() -> Result<String, BotError> {
+    use snafu::ResultExt;
+    let path = format!("heap-{}.prof", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    let c_path = std::ffi::CString::new(path.to_owned())
+        .whatever_context::<&str, BotError>("Invalid heap profile path")?;
+    // SAFETY: `prof.dump` takes a NUL terminated file name which outlives the call
+    unsafe { tikv_jemalloc_ctl::raw::write(b"prof.dump\0", c_path.as_ptr()) }?;
+    Ok(path)
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Shows jemalloc statistics and optionally dumps a heap profile
+pub async fn memory(
+    ctx: Context<'_>,
+    #[description = "Write a heap profile (requires the `profiling` feature)"] dump: Option<bool>,
+) -> Result<(), BotError> {
+    use tikv_jemalloc_ctl::{arenas, epoch, stats};
+    epoch::advance()?;
+
+    let narenas = arenas::narenas::read()?;
+    // SAFETY: `arenas.page` is a `size_t`
+    let page_size = unsafe { tikv_jemalloc_ctl::raw::read::<usize>(b"arenas.page\0") }?;
+    let table = arena_table((0..narenas).filter_map(arena_stats), page_size);
+
+    let dump = if dump.unwrap_or(false) {
+        #[cfg(feature = "profiling")]
+        let message = match dump_heap_profile() {
+            Ok(path) => format!("✅ Heap profile written to `{path}`"),
+            Err(e) => format!("❌ Failed to dump heap profile: {e}"),
+        };
+        #[cfg(not(feature = "profiling"))]
+        let message = "❌ Built without the `profiling` feature".to_string();
+        Some(message)
+    } else {
+        None
+    };
+
+    let embed = CreateEmbed::new()
+        .title("🧠 Jemalloc")
+        .color(BLURPLE)
+        .field("allocated", mb(stats::allocated::read()?), true)
+        .field("active", mb(stats::active::read()?), true)
+        .field("resident", mb(stats::resident::read()?), true)
+        .field("mapped", mb(stats::mapped::read()?), true)
+        .field("retained", mb(stats::retained::read()?), true)
+        .field("metadata", mb(stats::metadata::read()?), true)
+        .description(format!("```\n{table}```"))
+        .timestamp(Timestamp::now());
+
+    let mut reply = CreateReply::default().embed(embed);
+    if let Some(dump) = dump {
+        reply = reply.content(dump);
+    }
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Shows tokio per-worker statistics
+pub async fn runtime(ctx: Context<'_>) -> Result<(), BotError> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let mut table = format!(
+        "{:>6} {:>10} {:>8} {:>10} {:>8}\n",
+        "worker", "busy", "parks", "polls", "steals"
+    );
+    for worker in 0..metrics.num_workers() {
+        #[cfg(tokio_unstable)]
+        let (polls, steals) = (
+            metrics.worker_poll_count(worker).to_string(),
+            metrics.worker_steal_count(worker).to_string(),
+        );
+        #[cfg(not(tokio_unstable))]
+        let (polls, steals) = ("n/a", "n/a");
+        writeln!(
+            table,
+            "{:>6} {:>10} {:>8} {:>10} {:>8}",
+            worker,
+            format!("{:.1?}", metrics.worker_total_busy_duration(worker)),
+            metrics.worker_park_count(worker),
+            polls,
+            steals,
+        )
+        .ok();
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title("🚀 Tokio")
+        .color(BLURPLE)
+        .field("workers", metrics.num_workers().to_string(), true)
+        .field("alive tasks", metrics.num_alive_tasks().to_string(), true)
+        .field(
+            "global queue",
+            metrics.global_queue_depth().to_string(),
+            true,
+        )
+        .description(format!("```\n{table}```"))
+        .timestamp(Timestamp::now());
+    if cfg!(not(tokio_unstable)) {
+        embed = embed.footer(CreateEmbedFooter::new(
+            "Poll and steal counts require building with RUSTFLAGS=\"--cfg tokio_unstable\"",
+        ));
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arena_table_fits_an_embed() {
+        let arena = |index: u32, threads: u32| ArenaStats {
+            index,
+            threads,
+            active_pages: threads as usize,
+            dirty_pages: 0,
+            resident: 0,
+        };
+        // 4 arenas per CPU on a 64 core host, half of them never used
+        let table = arena_table((0..256).map(|i| arena(i, i % 2)), 4096);
+        assert!(table.len() + "```\n```".len() <= 4096);
+        assert_eq!(table.lines().count(), 1 + SHOWN_ARENAS + 2);
+        assert!(table.contains("… 88 more arenas\n128 idle arenas not shown"));
+
+        let table = arena_table([arena(0, 1), arena(1, 0)], 4096);
+        assert_eq!(table.lines().count(), 3);
+        assert!(!table.contains("more arenas"));
+    }
+}
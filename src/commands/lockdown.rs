@@ -0,0 +1,195 @@
+use poise::{CreateReply, command};
+use serenity::all::{
+    colours::branding::{GREEN, RED},
+    *,
+};
+
+use super::{Context, check_admin};
+use crate::{
+    error::BotError,
+    moderation::{
+        post_log,
+        raid::{describe, lock, lockdown as current, unlock},
+    },
+};
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    subcommands("lockdown_on", "lockdown_off", "lockdown_status"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD",
+    name_localized("zh-CN", "锁定"),
+    description_localized("zh-CN", "在突袭时锁定服务器或解除锁定"),
+    ephemeral
+)]
+/// Locks the server down during a raid
+pub async fn lockdown(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    required_bot_permissions = "MANAGE_GUILD | MANAGE_CHANNELS",
+    rename = "on",
+    ephemeral
+)]
+/// Raises the verification level, pauses invites and slows text channels down
+pub async fn lockdown_on(ctx: Context<'_>) -> Result<(), BotError> {
+    let Some(guild) = ctx.guild().map(|g| g.clone()) else {
+        ctx.say("服务器未缓存，请稍后再试。").await?;
+        return Ok(());
+    };
+    ctx.defer_ephemeral().await?;
+    let cfg = ctx.data().cfg.load_full();
+    let raid = cfg
+        .moderation
+        .raid
+        .get(&guild.id)
+        .copied()
+        .unwrap_or_default();
+    let Some((lockdown, failed)) =
+        lock(ctx, &ctx.data().db, &guild, &raid, Some(ctx.author().id)).await?
+    else {
+        ctx.say("服务器已处于锁定状态。").await?;
+        return Ok(());
+    };
+    let mut lines = describe(&lockdown, raid.slowmode_secs);
+    if failed > 0 {
+        lines.push(format!("⚠️ {failed} 项更改失败，见日志"));
+    }
+    let embed = CreateEmbed::new()
+        .title("🔒 服务器已锁定")
+        .description(lines.join("\n"))
+        .color(RED)
+        .field("执行者", ctx.author().mention().to_string(), true)
+        .footer(CreateEmbedFooter::new("使用 /lockdown off 解除锁定"))
+        .timestamp(Timestamp::now());
+    post_log(ctx, &cfg, guild.id, embed.clone()).await;
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    required_bot_permissions = "MANAGE_GUILD | MANAGE_CHANNELS",
+    rename = "off",
+    ephemeral
+)]
+/// Restores the settings the lockdown changed
+pub async fn lockdown_off(
+    ctx: Context<'_>,
+    #[description = "Without a recorded lockdown, remove the lockdown slowmode and resume invites"]
+    force: Option<bool>,
+) -> Result<(), BotError> {
+    let Some(guild) = ctx.guild().map(|g| g.clone()) else {
+        ctx.say("服务器未缓存，请稍后再试。").await?;
+        return Ok(());
+    };
+    ctx.defer_ephemeral().await?;
+    let cfg = ctx.data().cfg.load_full();
+    let raid = cfg
+        .moderation
+        .raid
+        .get(&guild.id)
+        .copied()
+        .unwrap_or_default();
+    let force = force.unwrap_or(false);
+    let Some((lockdown, recorded, failed)) =
+        unlock(ctx, &ctx.data().db, &guild, &raid, force).await?
+    else {
+        ctx.say(format!(
+            "没有记录中的锁定，未做任何更改。若服务器确实仍被锁定，可使用 `force: True` \
+             移除 {}s 的慢速模式并恢复邀请 (验证等级保持不变)。",
+            raid.slowmode_secs
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let mut lines = Vec::new();
+    if let Some(level) = lockdown.verification {
+        lines.push(format!("验证等级恢复为 {level:?}"));
+    }
+    if lockdown.invites_paused {
+        lines.push("恢复邀请".to_string());
+    }
+    if !lockdown.slowmodes.is_empty() {
+        lines.push(format!(
+            "恢复 {} 个文字频道的慢速模式",
+            lockdown.slowmodes.len()
+        ));
+    }
+    if lines.is_empty() {
+        lines.push("无需更改".to_string());
+    }
+    if !recorded {
+        lines.push("ℹ️ 没有记录中的锁定，已强制解除，验证等级保持不变".to_string());
+    }
+    if failed > 0 {
+        lines.push(format!("⚠️ {failed} 项更改失败，见日志"));
+    }
+    let embed = CreateEmbed::new()
+        .title("🔓 已解除锁定")
+        .description(lines.join("\n"))
+        .color(GREEN)
+        .field("执行者", ctx.author().mention().to_string(), true)
+        .timestamp(Timestamp::now());
+    post_log(ctx, &cfg, guild.id, embed.clone()).await;
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "status",
+    ephemeral
+)]
+/// Shows whether the server is locked down and the anti-raid thresholds
+pub async fn lockdown_status(ctx: Context<'_>) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let cfg = ctx.data().cfg.load_full();
+    let mut embed = match current(&ctx.data().db, guild_id).await? {
+        Some(lockdown) => {
+            let by = match lockdown.by {
+                Some(id) => id.mention().to_string(),
+                None => "自动".to_string(),
+            };
+            CreateEmbed::new()
+                .title("🔒 锁定中")
+                .color(RED)
+                .field(
+                    "开始于",
+                    format!("<t:{}:R>", lockdown.since.timestamp()),
+                    true,
+                )
+                .field("执行者", by, true)
+        }
+        None => CreateEmbed::new().title("🔓 未锁定").color(GREEN),
+    };
+    embed = match cfg.moderation.raid.get(&guild_id) {
+        Some(raid) => embed.field(
+            "突袭检测",
+            format!(
+                "{}s 内 {} 名成员或 {} 个新账号 (不足 {} 天)\n自动锁定: {}，慢速模式 {}s",
+                raid.window_secs,
+                raid.joins,
+                raid.new_accounts,
+                raid.account_age_days,
+                if raid.lockdown { "开启" } else { "关闭" },
+                raid.slowmode_secs
+            ),
+            false,
+        ),
+        None => embed.field("突袭检测", "未在配置中启用", false),
+    };
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use poise::command;
+use serenity::all::MessageBuilder;
+
+use super::Context;
+use crate::error::BotError;
+
+const DEFAULT_TIMEOUT_MINUTES: u64 = 10;
+
+#[command(
+    slash_command,
+    subcommands("set", "show", "reset"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    ephemeral
+)]
+/// Changes log verbosity at runtime
+pub async fn loglevel(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Temporarily applies a log filter directive, e.g. `serenity=debug`
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Filter directive in RUST_LOG syntax"] directive: String,
+    #[description = "Minutes before reverting, defaults to 10"]
+    #[min = 1]
+    #[max = 1440]
+    minutes: Option<u64>,
+) -> Result<(), BotError> {
+    let minutes = minutes.unwrap_or(DEFAULT_TIMEOUT_MINUTES);
+    ctx.data()
+        .log
+        .set_temporary(&directive, Duration::from_secs(minutes * 60))?;
+    ctx.say(
+        MessageBuilder::new()
+            .push("✅ Log filter set to ")
+            .push_mono_safe(&directive)
+            .push(format!(", reverting in {minutes} minutes"))
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Shows the log filter in effect
+pub async fn show(ctx: Context<'_>) -> Result<(), BotError> {
+    let log = &ctx.data().log;
+    ctx.say(
+        MessageBuilder::new()
+            .push("Current: ")
+            .push_mono_safe(log.current())
+            .push("\nBase: ")
+            .push_mono_safe(log.base())
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Restores the configured log filter immediately
+pub async fn reset(ctx: Context<'_>) -> Result<(), BotError> {
+    let log = &ctx.data().log;
+    log.reset()?;
+    ctx.say(
+        MessageBuilder::new()
+            .push("✅ Log filter restored to ")
+            .push_mono_safe(log.base())
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,54 +1,117 @@
+mod analytics;
+mod audit;
+mod automod;
+mod backup;
 // mod cookie;
+mod debug;
+mod lockdown;
+mod loglevel;
+mod moderation;
+mod pages;
+mod purge;
+mod server;
+mod stats;
+mod sync;
 mod system;
+mod timezone;
 
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use analytics::*;
 use arc_swap::ArcSwap;
+use audit::*;
+use automod::*;
+use backup::*;
 // use cookie::*;
-use owo_colors::OwoColorize;
+use debug::*;
+use lockdown::*;
+use loglevel::*;
+use moderation::*;
 use poise::command;
+use purge::*;
+use server::*;
+use snafu::OptionExt;
+use stats::*;
+use sync::*;
 use system::*;
-use tracing::{error, info};
+use timezone::*;
+use tracing::{error, info, warn};
 
-use crate::{config::BotCfg, database::BotDatabase, error::BotError};
+use crate::{
+    config::BotCfg, database::BotDatabase, error::BotError, logging::LogControl, metrics,
+    moderation::AutoMod, sampler::Sampler, utils::Zone,
+};
 
 pub type Context<'a> = poise::Context<'a, Data, BotError>;
 
-// pub async fn check_admin(ctx: Context<'_>) -> Result<bool, BotError> {
-//     let user_id = ctx.author().id;
-//     if ctx
-//         .data()
-//         .cfg
-//         .load()
-//         .extra_admin_user_ids
-//         .contains(&user_id)
-//     {
-//         return Ok(true);
-//     }
-//     Ok(ctx
-//         .author_member()
-//         .await
-//         .whatever_context::<&str, BotError>("Failed to get member information")?
-//         .roles
-//         .iter()
-//         .any(|&id| ctx.data().cfg.load().admin_role_ids.contains(&id)))
-// }
+/// Configured admins and members holding one of the admin roles, owners skip this check
+pub async fn check_admin(ctx: Context<'_>) -> Result<bool, BotError> {
+    let user_id = ctx.author().id;
+    if ctx
+        .data()
+        .cfg
+        .load()
+        .extra_admin_user_ids
+        .contains(&user_id)
+    {
+        return Ok(true);
+    }
+    Ok(ctx
+        .author_member()
+        .await
+        .whatever_context::<&str, BotError>("Failed to get member information")?
+        .roles
+        .iter()
+        .any(|&id| ctx.data().cfg.load().admin_role_ids.contains(&id)))
+}
 
 #[derive(Debug)]
 pub struct Data {
     db: BotDatabase,
-    #[allow(dead_code)]
+    sampler: Sampler,
+    log: LogControl,
     cfg: Arc<ArcSwap<BotCfg>>,
+    automod: AutoMod,
+}
+
+/// Error kind recorded for a failed invocation, checks and parse errors never reach the command
+fn failure_kind(error: &poise::FrameworkError<'_, Data, BotError>) -> &'static str {
+    use poise::FrameworkError::*;
+    match error {
+        Command { error, .. } => error.kind(),
+        CommandPanic { .. } => "panic",
+        ArgumentParse { .. } | CommandStructureMismatch { .. } | SubcommandRequired { .. } => {
+            "argument"
+        }
+        CooldownHit { .. } => "cooldown",
+        MissingBotPermissions { .. } => "bot_permissions",
+        MissingUserPermissions { .. }
+        | NotAnOwner { .. }
+        | GuildOnly { .. }
+        | DmOnly { .. }
+        | NsfwOnly { .. }
+        | CommandCheckFailed { .. } => "check",
+        _ => "other",
+    }
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, BotError>) {
     // This is our custom error handler
     // They are many errors that can occur, so we only handle the ones we want to customize
     // and forward the rest to the default handler
+    if let poise::FrameworkError::Setup { error, .. } = error {
+        panic!("Failed to start bot: {error}");
+    }
+    // Every failure tied to an invocation counts, not only errors returned by commands
+    if let Some(ctx) = error.ctx() {
+        record_usage(ctx, Some(failure_kind(&error))).await;
+    }
     match error {
-        poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {error}"),
         poise::FrameworkError::Command { error, ctx, .. } => {
-            error!("Error in command `{}`: {}", ctx.command().name, error);
+            error!(command = %ctx.command().qualified_name, "Command failed: {}", error);
         }
         error => {
             if let Err(e) = poise::builtins::on_error(error).await {
@@ -58,14 +121,88 @@ async fn on_error(error: poise::FrameworkError<'_, Data, BotError>) {
     }
 }
 
+/// Time since `pre_command` ran for this invocation
+async fn elapsed(ctx: Context<'_>) -> Duration {
+    ctx.invocation_data::<Instant>()
+        .await
+        .map_or(Duration::ZERO, |start| start.elapsed())
+}
+
+/// The zone for the invoking user: their own, then the guild's, then the configured one
+async fn zone(ctx: Context<'_>) -> Result<Zone, BotError> {
+    Ok(
+        match ctx
+            .data()
+            .db
+            .timezone()
+            .resolve(ctx.author().id, ctx.guild_id())
+            .await?
+        {
+            Some(tz) => Zone::Iana(tz),
+            None => ctx.data().cfg.load().zone(),
+        },
+    )
+}
+
+/// Export metrics and persist the invocation once a command has finished
+async fn record_usage(ctx: Context<'_>, error_kind: Option<&str>) {
+    let command = &ctx.command().qualified_name;
+    let elapsed = elapsed(ctx).await;
+    metrics::record_command(command, elapsed, error_kind);
+    if let Err(e) = ctx
+        .data()
+        .db
+        .usage()
+        .record(
+            command,
+            ctx.guild_id(),
+            ctx.channel_id(),
+            ctx.author().id,
+            elapsed,
+            error_kind,
+        )
+        .await
+    {
+        warn!("Failed to record command usage: {}", e);
+    }
+}
+
 #[command(prefix_command, owners_only)]
 async fn register(ctx: Context<'_>) -> Result<(), BotError> {
     Ok(poise::builtins::register_application_commands_buttons(ctx).await?)
 }
 
+/// Every command the bot serves
+pub fn commands() -> Vec<poise::Command<Data, BotError>> {
+    vec![
+        analytics(),
+        audit(),
+        automod(),
+        backup(),
+        ban(),
+        case(),
+        channeltree(),
+        manage_commands(),
+        debug(),
+        guilds_info(),
+        kick(),
+        lockdown(),
+        loglevel(),
+        purge(),
+        register(),
+        serverinfo(),
+        stats(),
+        system_info(),
+        timeout(),
+        timezone(),
+        unban(),
+        warn(),
+    ]
+}
+
 fn option() -> poise::FrameworkOptions<Data, BotError> {
     poise::FrameworkOptions {
-        commands: vec![guilds_info(), register(), system_info()],
+        commands: commands(),
         on_error: |error| {
             Box::pin(async {
                 on_error(error).await;
@@ -75,28 +212,52 @@ fn option() -> poise::FrameworkOptions<Data, BotError> {
         pre_command: |ctx| {
             Box::pin(async move {
                 info!(
-                    "Command: {}\tUser: {}\tGuild: {}",
-                    ctx.command().name.green(),
-                    ctx.author().name.green(),
-                    ctx.guild()
-                        .map(|g| g.name.to_owned())
-                        .unwrap_or("DM".to_string())
-                        .green()
-                )
+                    command = %ctx.command().qualified_name,
+                    user_id = %ctx.author().id,
+                    user = %ctx.author().name,
+                    guild_id = ctx.guild_id().map(|id| id.get()),
+                    "Command invoked"
+                );
+                ctx.set_invocation_data(Instant::now()).await;
+            })
+        },
+        post_command: |ctx| {
+            Box::pin(async move {
+                record_usage(ctx, None).await;
             })
         },
         ..Default::default()
     }
 }
 
-pub fn framework(db: BotDatabase, cfg: Arc<ArcSwap<BotCfg>>) -> poise::Framework<Data, BotError> {
+pub fn framework(
+    db: BotDatabase,
+    sampler: Sampler,
+    log: LogControl,
+    cfg: Arc<ArcSwap<BotCfg>>,
+    automod: AutoMod,
+) -> poise::Framework<Data, BotError> {
     poise::Framework::builder()
         .options(option())
-        .setup(|_, _, _| {
+        .setup(|ctx, _, framework| {
             Box::pin(async move {
                 // This is run when the framework is set up
                 info!("Framework has been set up!");
-                Ok(Data { db, cfg })
+                let dev_guild_ids = cfg.load().dev_guild_ids.to_owned();
+                let commands = &framework.options().commands;
+                for scope in scopes(&dev_guild_ids) {
+                    let served = served(commands, scope, &dev_guild_ids);
+                    if let Err(e) = sync(&ctx.http, served, scope).await {
+                        warn!(%scope, "Failed to sync application commands: {}", e);
+                    }
+                }
+                Ok(Data {
+                    db,
+                    sampler,
+                    log,
+                    cfg,
+                    automod,
+                })
             })
         })
         .build()
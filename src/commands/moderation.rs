@@ -0,0 +1,473 @@
+use std::{fmt::Write as _, time::Duration};
+
+use chrono::Utc;
+use poise::{ChoiceParameter, CreateReply, command};
+use serenity::all::{colours::branding::YELLOW, *};
+use snafu::OptionExt;
+
+use super::{Context, check_admin, pages};
+use crate::{
+    error::BotError,
+    moderation::{case_embed, human, notify, open_case, post_log, retract, style},
+    repo::CaseAction,
+};
+
+/// Discord keeps at most this many characters of an audit log reason
+const AUDIT_REASON_LEN: usize = 512;
+
+/// How long a member stays timed out
+#[derive(ChoiceParameter, Clone, Copy, Debug)]
+pub enum Length {
+    #[name = "60s"]
+    Minute,
+    #[name = "5m"]
+    FiveMinutes,
+    #[name = "10m"]
+    TenMinutes,
+    #[name = "1h"]
+    Hour,
+    #[name = "1d"]
+    Day,
+    #[name = "1w"]
+    Week,
+}
+
+impl Length {
+    pub(super) fn duration(self) -> Duration {
+        Duration::from_secs(match self {
+            Length::Minute => 60,
+            Length::FiveMinutes => 5 * 60,
+            Length::TenMinutes => 10 * 60,
+            Length::Hour => 60 * 60,
+            Length::Day => 24 * 60 * 60,
+            Length::Week => 7 * 24 * 60 * 60,
+        })
+    }
+}
+
+/// Whether `target` ranks at or above `moderator`, members without roles rank lowest
+fn outranks(guild: &Guild, target: &Member, moderator: &Member) -> bool {
+    if moderator.user.id == guild.owner_id {
+        return false;
+    }
+    if target.user.id == guild.owner_id {
+        return true;
+    }
+    let top = |member| guild.member_highest_role(member).map(|r| r.position);
+    match (top(target), top(moderator)) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(target), Some(moderator)) => target >= moderator,
+    }
+}
+
+/// Why the invoker may not act on `user_id`, `None` when they may
+async fn protected(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Option<&'static str>, BotError> {
+    let cfg = ctx.data().cfg.load_full();
+    if cfg.extra_admin_user_ids.contains(&user_id) {
+        return Ok(Some("不能对管理员执行管理操作。"));
+    }
+    // someone who already left can still be banned
+    let Ok(target) = guild_id.member(ctx, user_id).await else {
+        return Ok(None);
+    };
+    if target
+        .roles
+        .iter()
+        .any(|id| cfg.admin_role_ids.contains(id))
+    {
+        return Ok(Some("不能对管理员执行管理操作。"));
+    }
+    let moderator = ctx
+        .author_member()
+        .await
+        .whatever_context::<&str, BotError>("Failed to get member information")?
+        .into_owned();
+    Ok(match ctx.guild() {
+        Some(guild) if outranks(&guild, &target, &moderator) => {
+            Some("对象的最高身份组不低于你的，无法执行管理操作。")
+        }
+        Some(_) => None,
+        None => Some("服务器未缓存，无法比较身份组，请稍后再试。"),
+    })
+}
+
+/// Carry out `action` on `user`, then open a case and announce it
+///
+/// `apply` receives the audit log reason. Kicked and banned users no longer share a server with
+/// the bot, so they are messaged before the action instead of after it and told again when the
+/// action fails.
+async fn moderate(
+    ctx: Context<'_>,
+    action: CaseAction,
+    user: &User,
+    reason: Option<String>,
+    duration: Option<Duration>,
+    apply: impl AsyncFnOnce(GuildId, &str) -> Result<(), serenity::Error>,
+) -> Result<(), BotError> {
+    let guild_id = ctx
+        .guild_id()
+        .whatever_context::<&str, BotError>("Moderation commands only work in guilds")?;
+    if user.id == ctx.author().id || user.id == ctx.cache().current_user().id {
+        ctx.say("❌ 不能对自己或 Bot 执行管理操作。").await?;
+        return Ok(());
+    }
+    ctx.defer_ephemeral().await?;
+    if action != CaseAction::Unban
+        && let Some(why) = protected(ctx, guild_id, user.id).await?
+    {
+        ctx.say(format!("❌ {why}")).await?;
+        return Ok(());
+    }
+    let cfg = ctx.data().cfg.load_full();
+    let dm = cfg.moderation.dm_targets;
+    let guild = ctx
+        .guild()
+        .map(|g| g.name.clone())
+        .unwrap_or_else(|| "服务器".to_string());
+    let reason = reason.as_deref();
+    let audit_reason = format!("{}: {}", ctx.author().name, reason.unwrap_or("-"))
+        .chars()
+        .take(AUDIT_REASON_LEN)
+        .collect::<String>();
+
+    let early = matches!(action, CaseAction::Kick | CaseAction::Ban);
+    let mut notified = None;
+    if dm && early {
+        notified = Some(notify(ctx, user.id, &guild, action, reason, duration).await);
+    }
+    if let Err(e) = apply(guild_id, &audit_reason).await {
+        if notified == Some(true) {
+            retract(ctx, user.id, &guild, action).await;
+        }
+        ctx.say(format!("❌ 操作失败: {e}")).await?;
+        return Err(e.into());
+    }
+    if dm && !early {
+        notified = Some(notify(ctx, user.id, &guild, action, reason, duration).await);
+    }
+
+    let case = open_case(
+        ctx,
+        &cfg,
+        &ctx.data().db,
+        guild_id,
+        action,
+        user.id,
+        ctx.author().id,
+        reason,
+        duration,
+    )
+    .await?;
+    let embed = case_embed(&case)?;
+    let note = match notified {
+        Some(true) => "已私信通知对象",
+        Some(false) => "无法私信对象",
+        None => "未发送私信",
+    };
+    ctx.send(CreateReply::default().embed(embed.footer(CreateEmbedFooter::new(note))))
+        .await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "MODERATE_MEMBERS",
+    name_localized("zh-CN", "警告"),
+    description_localized("zh-CN", "警告成员并记录案例"),
+    ephemeral
+)]
+/// Warns a member and records a case
+pub async fn warn(
+    ctx: Context<'_>,
+    #[description = "Member to warn"] user: User,
+    #[description = "Shown to the member and in the mod log"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<(), BotError> {
+    moderate(ctx, CaseAction::Warn, &user, reason, None, async |_, _| {
+        Ok(())
+    })
+    .await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "MODERATE_MEMBERS",
+    name_localized("zh-CN", "禁言"),
+    description_localized("zh-CN", "暂时禁止成员发言并记录案例"),
+    ephemeral
+)]
+/// Times a member out and records a case
+pub async fn timeout(
+    ctx: Context<'_>,
+    #[description = "Member to time out"] user: User,
+    #[description = "How long the timeout lasts"] length: Length,
+    #[description = "Shown to the member and in the mod log"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<(), BotError> {
+    let duration = length.duration();
+    let until = Utc::now() + duration;
+    moderate(
+        ctx,
+        CaseAction::Timeout,
+        &user,
+        reason,
+        Some(duration),
+        async |guild_id, audit_reason| {
+            guild_id
+                .edit_member(
+                    ctx,
+                    user.id,
+                    EditMember::new()
+                        .disable_communication_until_datetime(until.into())
+                        .audit_log_reason(audit_reason),
+                )
+                .await
+                .map(|_| ())
+        },
+    )
+    .await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "KICK_MEMBERS",
+    name_localized("zh-CN", "踢出"),
+    description_localized("zh-CN", "将成员踢出服务器并记录案例"),
+    ephemeral
+)]
+/// Kicks a member and records a case
+pub async fn kick(
+    ctx: Context<'_>,
+    #[description = "Member to kick"] user: User,
+    #[description = "Shown to the member and in the mod log"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<(), BotError> {
+    moderate(
+        ctx,
+        CaseAction::Kick,
+        &user,
+        reason,
+        None,
+        async |guild_id, audit_reason| guild_id.kick_with_reason(ctx, user.id, audit_reason).await,
+    )
+    .await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "BAN_MEMBERS",
+    name_localized("zh-CN", "封禁"),
+    description_localized("zh-CN", "封禁用户并记录案例"),
+    ephemeral
+)]
+/// Bans a user and records a case
+pub async fn ban(
+    ctx: Context<'_>,
+    #[description = "User to ban"] user: User,
+    #[description = "Shown to the user and in the mod log"]
+    #[max_length = 512]
+    reason: Option<String>,
+    #[description = "Delete their messages from the last days, defaults to 0"]
+    #[min = 0]
+    #[max = 7]
+    delete_days: Option<u8>,
+) -> Result<(), BotError> {
+    moderate(
+        ctx,
+        CaseAction::Ban,
+        &user,
+        reason,
+        None,
+        async |guild_id, audit_reason| {
+            guild_id
+                .ban_with_reason(ctx, user.id, delete_days.unwrap_or(0), audit_reason)
+                .await
+        },
+    )
+    .await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "BAN_MEMBERS",
+    name_localized("zh-CN", "解封"),
+    description_localized("zh-CN", "解除封禁并记录案例"),
+    ephemeral
+)]
+/// Lifts a ban and records a case
+pub async fn unban(
+    ctx: Context<'_>,
+    #[description = "User to unban, accepts an ID"] user: User,
+    #[description = "Shown in the mod log"]
+    #[max_length = 512]
+    reason: Option<String>,
+) -> Result<(), BotError> {
+    moderate(
+        ctx,
+        CaseAction::Unban,
+        &user,
+        reason,
+        None,
+        async |guild_id, _| guild_id.unban(ctx, user.id).await,
+    )
+    .await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    subcommands("view", "edit_reason", "list"),
+    subcommand_required,
+    default_member_permissions = "MODERATE_MEMBERS",
+    name_localized("zh-CN", "案例"),
+    description_localized("zh-CN", "查看和编辑管理案例"),
+    ephemeral
+)]
+/// Moderation case history
+pub async fn case(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, guild_only, check = "check_admin", ephemeral)]
+/// Shows a case
+pub async fn view(
+    ctx: Context<'_>,
+    #[description = "Case number"]
+    #[min = 1]
+    number: i64,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    match ctx.data().db.case().get(guild_id, number).await? {
+        Some(case) => {
+            ctx.send(CreateReply::default().embed(case_embed(&case)?))
+                .await?;
+        }
+        None => {
+            ctx.say(format!("找不到案例 #{number}。")).await?;
+        }
+    }
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    rename = "edit-reason",
+    ephemeral
+)]
+/// Replaces the reason of a case
+pub async fn edit_reason(
+    ctx: Context<'_>,
+    #[description = "Case number"]
+    #[min = 1]
+    number: i64,
+    #[description = "New reason"]
+    #[max_length = 512]
+    reason: String,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let Some(case) = ctx
+        .data()
+        .db
+        .case()
+        .set_reason(guild_id, number, &reason)
+        .await?
+    else {
+        ctx.say(format!("找不到案例 #{number}。")).await?;
+        return Ok(());
+    };
+    let embed = case_embed(&case)?;
+    post_log(ctx, &ctx.data().cfg.load_full(), guild_id, embed.clone()).await;
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[command(slash_command, guild_only, check = "check_admin", ephemeral)]
+/// Lists the cases of a user, newest first
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Whose cases to list"] user: User,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let cases = ctx.data().db.case().list(guild_id, user.id).await?;
+    if cases.is_empty() {
+        ctx.say(format!("{} 没有任何案例记录。", user.name)).await?;
+        return Ok(());
+    }
+    let mut lines = Vec::with_capacity(cases.len());
+    for case in &cases {
+        let (title, _) = style(case.action.parse()?);
+        let mut line = format!(
+            "`#{}` {title} · <t:{}:d>",
+            case.case_number,
+            case.created_at.timestamp()
+        );
+        if let Some(secs) = case.duration_secs {
+            write!(line, " · {}", human(Duration::from_secs(secs as u64))).ok();
+        }
+        write!(line, "\n└ {}", case.reason.as_deref().unwrap_or("未填写")).ok();
+        lines.push(line);
+    }
+    let embed = CreateEmbed::new()
+        .title(format!("📋 {} 的案例记录 ({})", user.name, cases.len()))
+        .color(YELLOW);
+    pages::send(ctx, embed, &pages::split(&lines, pages::PAGE_LEN)).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_outranks() {
+        let mut guild = Guild::default();
+        guild.owner_id = UserId::new(1);
+        for (id, position) in [(10, 1), (11, 2)] {
+            let mut role = Role::default();
+            role.id = RoleId::new(id);
+            role.position = position;
+            guild.roles.insert(role.id, role);
+        }
+        let member = |id: u64, roles: &[u64]| {
+            let mut member = Member::default();
+            member.user.id = UserId::new(id);
+            member.roles = roles.iter().copied().map(RoleId::new).collect();
+            member
+        };
+        let (owner, low, high, plain) = (
+            member(1, &[]),
+            member(2, &[10]),
+            member(3, &[10, 11]),
+            member(4, &[]),
+        );
+
+        assert!(outranks(&guild, &high, &low));
+        assert!(!outranks(&guild, &low, &high));
+        // the same top role is not enough
+        assert!(outranks(&guild, &member(5, &[11]), &high));
+        assert!(!outranks(&guild, &plain, &low));
+        assert!(outranks(&guild, &low, &plain));
+        assert!(outranks(&guild, &owner, &high));
+        assert!(!outranks(&guild, &high, &owner));
+    }
+}
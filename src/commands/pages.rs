@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use poise::CreateReply;
+use serenity::all::*;
+
+use super::Context;
+use crate::error::BotError;
+
+/// Room left in an embed description once a page is wrapped in a code block
+pub const PAGE_LEN: usize = 4000;
+/// How long the navigation buttons stay active after the last press
+const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Pack `lines` into pages of at most `max` bytes, overlong lines get a page of their own
+pub fn split<S: AsRef<str>>(lines: impl IntoIterator<Item = S>, max: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+    for line in lines {
+        let line = line.as_ref();
+        if !page.is_empty() && page.len() + line.len() + 1 > max {
+            pages.push(std::mem::take(&mut page));
+        }
+        page.push_str(line);
+        page.push('\n');
+    }
+    if !page.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+/// Send `pages` as embeds with buttons to flip between them, for the invoker only
+pub async fn send(ctx: Context<'_>, embed: CreateEmbed, pages: &[String]) -> Result<(), BotError> {
+    let page = |i: usize| {
+        let mut embed = embed.clone().description(&pages[i]);
+        if pages.len() > 1 {
+            embed = embed.footer(CreateEmbedFooter::new(format!(
+                "第 {}/{} 页",
+                i + 1,
+                pages.len()
+            )));
+        }
+        embed
+    };
+    if pages.len() <= 1 {
+        let embed = if pages.is_empty() { embed } else { page(0) };
+        ctx.send(CreateReply::default().embed(embed)).await?;
+        return Ok(());
+    }
+
+    let prev = format!("{}prev", ctx.id());
+    let next = format!("{}next", ctx.id());
+    let buttons = |enabled: bool| {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(&prev).emoji('◀').disabled(!enabled),
+            CreateButton::new(&next).emoji('▶').disabled(!enabled),
+        ])]
+    };
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .embed(page(0))
+                .components(buttons(true)),
+        )
+        .await?;
+
+    let mut current = 0;
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter({
+            let id = ctx.id().to_string();
+            move |press| press.data.custom_id.starts_with(&id)
+        })
+        .timeout(TIMEOUT)
+        .await
+    {
+        current = if press.data.custom_id == next {
+            (current + 1) % pages.len()
+        } else {
+            (current + pages.len() - 1) % pages.len()
+        };
+        press
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embed(page(current)),
+                ),
+            )
+            .await?;
+    }
+    reply
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(page(current))
+                .components(buttons(false)),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert!(split(Vec::<String>::new(), 10).is_empty());
+        assert_eq!(
+            split(["aaaa", "bbbb", "cc", "dddddddddddd", "e"], 10),
+            vec!["aaaa\nbbbb\n", "cc\n", "dddddddddddd\n", "e\n"]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use poise::{CreateReply, command};
+use serenity::all::{colours::branding::GREEN, *};
+use tracing::warn;
+
+use super::{Context, check_admin};
+use crate::error::BotError;
+
+/// Discord only bulk deletes messages younger than 14 days, the hour covers slow requests
+const BULK_MAX_AGE_SECS: i64 = 14 * 24 * 60 * 60 - 60 * 60;
+/// Messages fetched per request and deleted per bulk request
+const BATCH: u8 = 100;
+/// Stop scanning history after this many messages, however few matched
+const SCAN_LIMIT: usize = 2000;
+
+/// Which messages `/purge` removes, pinned messages are always kept
+#[derive(Debug, Default)]
+struct Filter {
+    user: Option<UserId>,
+    /// Lowercased
+    contains: Option<String>,
+    bots_only: bool,
+    attachments_only: bool,
+    after: Option<MessageId>,
+}
+
+impl Filter {
+    fn matches(&self, message: &Message) -> bool {
+        !message.pinned
+            && self.user.is_none_or(|id| message.author.id == id)
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|text| message.content.to_lowercase().contains(text))
+            && (!self.bots_only || message.author.bot)
+            && (!self.attachments_only || !message.attachments.is_empty())
+            && self.after.is_none_or(|after| message.id > after)
+    }
+}
+
+/// A message ID or link
+fn parse_message_id(text: &str) -> Option<MessageId> {
+    let id = text.trim().rsplit('/').next()?.parse().ok()?;
+    (id != 0).then(|| MessageId::new(id))
+}
+
+/// Split into messages that can be bulk deleted and those that must go one by one
+fn partition(ids: &[MessageId], now: Timestamp) -> (Vec<MessageId>, Vec<MessageId>) {
+    ids.iter()
+        .partition(|id| now.unix_timestamp() - id.created_at().unix_timestamp() < BULK_MAX_AGE_SECS)
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    check = "check_admin",
+    default_member_permissions = "MANAGE_MESSAGES",
+    required_bot_permissions = "MANAGE_MESSAGES | READ_MESSAGE_HISTORY",
+    name_localized("zh-CN", "清理消息"),
+    description_localized("zh-CN", "按条件批量删除本频道的消息"),
+    ephemeral
+)]
+/// Deletes recent messages in this channel that match every given filter
+#[allow(clippy::too_many_arguments)]
+pub async fn purge(
+    ctx: Context<'_>,
+    #[description = "How many matching messages to delete"]
+    #[min = 1]
+    #[max = 500]
+    count: u32,
+    #[description = "Only messages from this user"] user: Option<User>,
+    #[description = "Only messages containing this text, ignoring case"] contains: Option<String>,
+    #[description = "Only messages from bots"] bots_only: Option<bool>,
+    #[description = "Only messages with attachments"] attachments_only: Option<bool>,
+    #[description = "Only messages before this message ID or link"] before: Option<String>,
+    #[description = "Only messages after this message ID or link"] after: Option<String>,
+) -> Result<(), BotError> {
+    let parse = |text: Option<String>| match text {
+        Some(text) => parse_message_id(&text).map(Some).ok_or(text),
+        None => Ok(None),
+    };
+    let (before, after) = match (parse(before), parse(after)) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(text), _) | (_, Err(text)) => {
+            ctx.say(format!("❌ 无法识别的消息 ID 或链接: `{text}`"))
+                .await?;
+            return Ok(());
+        }
+    };
+    let filter = Filter {
+        user: user.map(|u| u.id),
+        contains: contains.map(|c| c.to_lowercase()),
+        bots_only: bots_only.unwrap_or(false),
+        attachments_only: attachments_only.unwrap_or(false),
+        after,
+    };
+    let count = count as usize;
+    ctx.defer_ephemeral().await?;
+
+    // History comes newest first, so anything at or below `after` ends the scan
+    let channel = ctx.channel_id();
+    let mut cursor = before;
+    let mut scanned = 0;
+    let mut matched = Vec::new();
+    'scan: while matched.len() < count && scanned < SCAN_LIMIT {
+        let mut request = GetMessages::new().limit(BATCH);
+        if let Some(cursor) = cursor {
+            request = request.before(cursor);
+        }
+        let batch = channel.messages(ctx, request).await?;
+        for message in &batch {
+            scanned += 1;
+            if filter.after.is_some_and(|after| message.id <= after) {
+                break 'scan;
+            }
+            if filter.matches(message) {
+                matched.push(message.id);
+                if matched.len() == count {
+                    break 'scan;
+                }
+            }
+        }
+        if batch.len() < BATCH as usize {
+            break;
+        }
+        cursor = batch.last().map(|m| m.id);
+    }
+
+    // Serenity's HTTP client waits out rate limits, so both loops just keep going
+    let (bulk, single) = partition(&matched, Timestamp::now());
+    let mut deleted = Vec::with_capacity(matched.len());
+    let mut failed = 0;
+    for chunk in bulk.chunks(BATCH as usize) {
+        let result = match chunk {
+            [id] => channel.delete_message(ctx, id).await,
+            _ => channel.delete_messages(ctx, chunk).await,
+        };
+        match result {
+            Ok(()) => deleted.extend_from_slice(chunk),
+            Err(e) => {
+                failed += chunk.len();
+                warn!(%channel, "Failed to bulk delete messages: {}", e);
+            }
+        }
+    }
+    for id in &single {
+        match channel.delete_message(ctx, id).await {
+            Ok(()) => deleted.push(*id),
+            Err(e) => {
+                failed += 1;
+                warn!(%channel, message_id = %id, "Failed to delete message: {}", e);
+            }
+        }
+    }
+    ctx.data().db.message().delete(&deleted).await?;
+
+    let mut embed = CreateEmbed::new()
+        .title("🧹 清理完成")
+        .color(GREEN)
+        .field("已删除", deleted.len().to_string(), true)
+        .field(
+            "批量 / 逐条",
+            format!("{} / {}", bulk.len(), single.len()),
+            true,
+        )
+        .field("已扫描", scanned.to_string(), true);
+    if failed > 0 {
+        embed = embed.field("失败", failed.to_string(), true);
+    }
+    if matched.len() < count && scanned >= SCAN_LIMIT {
+        embed = embed.footer(CreateEmbedFooter::new(format!(
+            "已达到单次扫描上限 {SCAN_LIMIT} 条"
+        )));
+    }
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::test_snowflake;
+
+    fn message(id: u64, author: u64, bot: bool, content: &str) -> Message {
+        let mut message = Message::default();
+        message.id = MessageId::new(id);
+        message.author.id = UserId::new(author);
+        message.author.bot = bot;
+        message.content = content.to_string();
+        message
+    }
+
+    #[test]
+    fn test_filter() {
+        let plain = message(10, 1, false, "Hello World");
+        let bot = message(11, 2, true, "beep");
+        let mut pinned = message(12, 1, false, "hello");
+        pinned.pinned = true;
+        let mut upload = message(13, 1, false, "");
+        upload.attachments = vec![
+            serenity::json::from_str(
+                r#"{"id": "1", "filename": "a.png", "size": 1, "url": "", "proxy_url": ""}"#,
+            )
+            .unwrap(),
+        ];
+
+        let all = Filter::default();
+        assert!(all.matches(&plain) && all.matches(&bot) && all.matches(&upload));
+        assert!(!all.matches(&pinned));
+
+        let filter = Filter {
+            user: Some(UserId::new(1)),
+            contains: Some("world".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&plain));
+        assert!(!filter.matches(&bot) && !filter.matches(&upload));
+
+        let bots = Filter {
+            bots_only: true,
+            ..Default::default()
+        };
+        assert!(bots.matches(&bot) && !bots.matches(&plain));
+
+        let uploads = Filter {
+            attachments_only: true,
+            after: Some(MessageId::new(10)),
+            ..Default::default()
+        };
+        assert!(uploads.matches(&upload) && !uploads.matches(&plain));
+    }
+
+    #[test]
+    fn test_partition_by_age() {
+        let now = Timestamp::now();
+        let id_at = |secs_ago| MessageId::new(test_snowflake(now, secs_ago));
+        let fresh = id_at(60);
+        let old = id_at(15 * 24 * 60 * 60);
+        let (bulk, single) = partition(&[fresh, old], now);
+        assert_eq!((bulk, single), (vec![fresh], vec![old]));
+    }
+
+    #[test]
+    fn test_parse_message_id() {
+        assert_eq!(parse_message_id(" 42 "), Some(MessageId::new(42)));
+        assert_eq!(
+            parse_message_id("https://discord.com/channels/1/2/42"),
+            Some(MessageId::new(42))
+        );
+        assert_eq!(parse_message_id("0"), None);
+        assert_eq!(parse_message_id("nope"), None);
+    }
+}
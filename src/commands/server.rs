@@ -0,0 +1,238 @@
+use poise::{CreateReply, command};
+use serenity::all::{colours::branding::BLURPLE, *};
+
+use super::{Context, pages};
+use crate::{
+    error::BotError,
+    utils::{ChannelNode, ChannelTree},
+};
+
+/// Icon and label for a channel type
+fn kind(kind: ChannelType) -> (&'static str, &'static str) {
+    match kind {
+        ChannelType::Category => ("📁", "分类"),
+        ChannelType::Text => ("💬", "文字"),
+        ChannelType::News => ("📢", "公告"),
+        ChannelType::Voice => ("🔊", "语音"),
+        ChannelType::Stage => ("🎙️", "舞台"),
+        ChannelType::Forum => ("🗂️", "论坛"),
+        ChannelType::PublicThread | ChannelType::NewsThread => ("🧵", "子区"),
+        ChannelType::PrivateThread => ("🔒", "私密子区"),
+        _ => ("❔", "其他"),
+    }
+}
+
+/// One line per channel, indented by depth, with its type, slowmode and NSFW flags
+fn render(roots: &[ChannelNode<'_>]) -> Vec<String> {
+    roots
+        .iter()
+        .flat_map(ChannelNode::walk)
+        .map(|(depth, channel)| {
+            let (icon, label) = kind(channel.kind);
+            let mut flags = vec![label.to_string()];
+            if let Some(secs) = channel.rate_limit_per_user.filter(|&s| s > 0) {
+                flags.push(format!("慢速 {secs}s"));
+            }
+            if channel.nsfw {
+                flags.push("NSFW".to_string());
+            }
+            format!(
+                "{}{icon} {} ({})",
+                "  ".repeat(depth),
+                channel.name,
+                flags.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// The bot's own member in `guild_id`, from the cache when possible
+pub(super) async fn bot_member(ctx: Context<'_>, guild_id: GuildId) -> Result<Member, BotError> {
+    let bot_id = ctx.cache().current_user().id;
+    let cached = ctx
+        .cache()
+        .guild(guild_id)
+        .and_then(|g| g.members.get(&bot_id).cloned());
+    Ok(match cached {
+        Some(member) => member,
+        None => guild_id.member(ctx, bot_id).await?,
+    })
+}
+
+/// Server-wide permissions of `member` from its roles, before any channel overwrites
+pub(super) fn guild_permissions(guild: &Guild, member: &Member) -> Permissions {
+    if guild.owner_id == member.user.id {
+        return Permissions::all();
+    }
+    let everyone = RoleId::new(guild.id.get());
+    let permissions = std::iter::once(&everyone)
+        .chain(&member.roles)
+        .filter_map(|id| guild.roles.get(id))
+        .fold(Permissions::empty(), |acc, role| acc | role.permissions);
+    if permissions.administrator() {
+        Permissions::all()
+    } else {
+        permissions
+    }
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    name_localized("zh-CN", "频道树"),
+    description_localized("zh-CN", "以树状结构显示服务器的分类、频道和子区")
+)]
+/// Shows the categories, channels and threads of this server as a tree
+pub async fn channeltree(
+    ctx: Context<'_>,
+    #[description = "Only show this category"]
+    #[channel_types("Category")]
+    category: Option<GuildChannel>,
+) -> Result<(), BotError> {
+    let rendered = ctx.guild().map(|guild| {
+        let tree = ChannelTree::new(&guild);
+        let roots = match category.as_ref().and_then(|c| guild.channels.get(&c.id)) {
+            Some(category) => vec![tree.subtree(category)],
+            None => tree.roots(),
+        };
+        (guild.name.clone(), render(&roots))
+    });
+    let Some((name, lines)) = rendered else {
+        ctx.say("服务器信息尚未缓存，请稍后再试。").await?;
+        return Ok(());
+    };
+    if lines.is_empty() {
+        ctx.say("没有可显示的频道。").await?;
+        return Ok(());
+    }
+    let pages = pages::split(&lines, pages::PAGE_LEN)
+        .into_iter()
+        .map(|page| format!("```\n{page}```"))
+        .collect::<Vec<_>>();
+    let embed = CreateEmbed::new()
+        .title(format!("🌳 {name} 的频道结构"))
+        .color(BLURPLE);
+    pages::send(ctx, embed, &pages).await
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    name_localized("zh-CN", "服务器信息"),
+    description_localized("zh-CN", "查看服务器的成员、加成、身份组和 Bot 权限")
+)]
+/// Shows members, boosts, roles and the bot's permissions in this server
+pub async fn serverinfo(ctx: Context<'_>) -> Result<(), BotError> {
+    let member = bot_member(ctx, ctx.guild_id().unwrap_or_default()).await?;
+    let embed = ctx.guild().map(|guild| {
+        let bots = guild.members.values().filter(|m| m.user.bot).count();
+        let online = guild
+            .presences
+            .values()
+            .filter(|p| p.status != OnlineStatus::Offline)
+            .count();
+        let count =
+            |f: fn(&ChannelType) -> bool| guild.channels.values().filter(|c| f(&c.kind)).count();
+        let text = count(|k| {
+            matches!(
+                k,
+                ChannelType::Text | ChannelType::News | ChannelType::Forum
+            )
+        });
+        let voice = count(|k| matches!(k, ChannelType::Voice | ChannelType::Stage));
+        let categories = count(|k| *k == ChannelType::Category);
+        let created = guild.id.created_at().unix_timestamp();
+        let permissions = guild_permissions(&guild, &member);
+        let permissions = if permissions.is_all() {
+            "Administrator（全部权限）".to_string()
+        } else {
+            permissions.get_permission_names().join(", ")
+        };
+
+        CreateEmbed::new()
+            .title(format!("🏠 {}", guild.name))
+            .color(BLURPLE)
+            .thumbnail(guild.icon_url().unwrap_or_default())
+            // row 0
+            .field("👑 所有者", guild.owner_id.mention().to_string(), true)
+            .field("🆔 服务器 ID", guild.id.to_string(), true)
+            .field(
+                "📅 创建于",
+                format!("<t:{created}:D>\n<t:{created}:R>"),
+                true,
+            )
+            // row 1
+            .field("👥 成员数", guild.member_count.to_string(), true)
+            .field(
+                "🤖 已缓存成员",
+                format!("{} 人 / {bots} 个 Bot", guild.members.len() - bots),
+                true,
+            )
+            .field("🟢 在线", online.to_string(), true)
+            // row 2
+            .field(
+                "💎 加成等级",
+                format!("{} 级", u8::from(guild.premium_tier)),
+                true,
+            )
+            .field(
+                "🚀 加成次数",
+                guild.premium_subscription_count.unwrap_or(0).to_string(),
+                true,
+            )
+            .field("🎭 身份组", guild.roles.len().to_string(), true)
+            // row 3
+            .field("📁 分类", categories.to_string(), true)
+            .field("💬 文字频道", text.to_string(), true)
+            .field("🔊 语音频道", voice.to_string(), true)
+            .field("🛡️ Bot 权限", permissions, false)
+            .timestamp(Timestamp::now())
+            .footer(CreateEmbedFooter::new(format!(
+                "活跃子区 {} 个",
+                guild.threads.len()
+            )))
+    });
+    let Some(embed) = embed else {
+        ctx.say("服务器信息尚未缓存，请稍后再试。").await?;
+        return Ok(());
+    };
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::test_channel;
+
+    fn channel(id: u64, kind: ChannelType, parent: Option<u64>) -> GuildChannel {
+        test_channel(id, kind, parent, 0)
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let mut guild = Guild::default();
+        let mut text = channel(2, ChannelType::Text, Some(1));
+        text.rate_limit_per_user = Some(10);
+        text.nsfw = true;
+        for c in [
+            channel(1, ChannelType::Category, None),
+            text,
+            channel(3, ChannelType::Voice, Some(1)),
+        ] {
+            guild.channels.insert(c.id, c);
+        }
+        guild.threads = vec![channel(4, ChannelType::PrivateThread, Some(2))];
+
+        let tree = ChannelTree::new(&guild);
+        assert_eq!(
+            render(&tree.roots()),
+            vec![
+                "📁 c1 (分类)",
+                "  💬 c2 (文字, 慢速 10s, NSFW)",
+                "    🔒 c4 (私密子区)",
+                "  🔊 c3 (语音)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Days, FixedOffset};
+use poise::{ChoiceParameter, CreateReply, command};
+use serenity::all::{colours::branding::BLURPLE, *};
+
+use super::{Context, analytics::Period, zone};
+use crate::{
+    error::BotError,
+    utils::{Zone, get_all_children_channels},
+};
+
+/// Entries shown per ranking
+const TOP: usize = 15;
+
+#[derive(ChoiceParameter, Clone, Copy, Debug)]
+pub enum Rank {
+    #[name = "messages"]
+    Messages,
+    #[name = "reactions received"]
+    ReactionsReceived,
+    #[name = "reactions given"]
+    ReactionsGiven,
+    #[name = "attachments"]
+    Attachments,
+}
+
+/// Bounds of the last `period` calendar days in `zone`, today included
+///
+/// Local days rarely line up with the UTC days of the rollups, the partial UTC days at either
+/// edge are then counted from the raw rows.
+fn window(
+    period: Period,
+    zone: Zone,
+) -> (Option<DateTime<FixedOffset>>, Option<DateTime<FixedOffset>>) {
+    let Some(days) = period.days() else {
+        return (None, None);
+    };
+    let today = zone.today();
+    let first = today - Days::new(days as u64 - 1);
+    let (from, to) = zone.date_window(first, today);
+    (Some(from), Some(to))
+}
+
+/// `channel` with the channels, threads and forum posts under it that are cached
+fn with_children(ctx: Context<'_>, channel: &GuildChannel) -> Vec<ChannelId> {
+    match ctx.guild() {
+        Some(guild) => get_all_children_channels(&guild, channel)
+            .iter()
+            .map(|c| c.id)
+            .collect(),
+        None => vec![channel.id],
+    }
+}
+
+async fn reply(
+    ctx: Context<'_>,
+    title: String,
+    zone: Zone,
+    rows: Vec<(Mention, u64)>,
+) -> Result<(), BotError> {
+    if rows.is_empty() {
+        ctx.say("该时间段内没有记录。").await?;
+        return Ok(());
+    }
+    let mut description = String::new();
+    for (i, (mention, count)) in rows.iter().take(TOP).enumerate() {
+        writeln!(description, "`#{:<2}` {mention} — **{count}**", i + 1).ok();
+    }
+    let embed = CreateEmbed::new()
+        .title(title)
+        .color(BLURPLE)
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!(
+            "共 {} 项 · 时区 {zone}",
+            rows.len()
+        )))
+        .timestamp(Timestamp::now());
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    subcommands("users", "channels", "threads"),
+    subcommand_required,
+    name_localized("zh-CN", "活跃统计"),
+    description_localized("zh-CN", "查看服务器成员和频道的活跃排行")
+)]
+/// Activity rankings of this server
+pub async fn stats(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, guild_only)]
+/// Ranks members by messages, reactions or attachments
+pub async fn users(
+    ctx: Context<'_>,
+    #[description = "What to rank by, defaults to messages"] rank: Option<Rank>,
+    #[description = "Time window, defaults to 7d"] period: Option<Period>,
+    #[description = "Only count this channel and its threads"]
+    #[channel_types("Category", "Text", "News", "Forum", "PublicThread", "PrivateThread")]
+    channel: Option<GuildChannel>,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let rank = rank.unwrap_or(Rank::Messages);
+    let period = period.unwrap_or(Period::Week);
+    let zone = zone(ctx).await?;
+    let (from, to) = window(period, zone);
+    let channels = channel.as_ref().map(|c| with_children(ctx, c));
+    let channels = channels.as_deref();
+    let db = &ctx.data().db;
+    let rows = match rank {
+        Rank::Messages => {
+            db.message()
+                .get_user_stats(guild_id, channels, from, to)
+                .await?
+        }
+        Rank::Attachments => {
+            db.message()
+                .get_attachment_stats(guild_id, channels, from, to)
+                .await?
+        }
+        Rank::ReactionsReceived => {
+            db.reaction()
+                .get_received_stats(guild_id, channels, from, to)
+                .await?
+        }
+        Rank::ReactionsGiven => {
+            db.reaction()
+                .get_given_stats(guild_id, channels, from, to)
+                .await?
+        }
+    };
+    let rows = rows
+        .into_iter()
+        .map(|(user_id, count)| (user_id.mention(), count))
+        .collect();
+    reply(
+        ctx,
+        format!("🏆 成员排行 · {} ({})", rank.name(), period.name()),
+        zone,
+        rows,
+    )
+    .await
+}
+
+#[command(slash_command, guild_only)]
+/// Ranks channels by messages
+pub async fn channels(
+    ctx: Context<'_>,
+    #[description = "Time window, defaults to 7d"] period: Option<Period>,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let period = period.unwrap_or(Period::Week);
+    let zone = zone(ctx).await?;
+    let (from, to) = window(period, zone);
+    let rows = ctx
+        .data()
+        .db
+        .message()
+        .get_channel_stats(guild_id, from, to)
+        .await?
+        .into_iter()
+        .map(|(channel_id, count)| (channel_id.mention(), count))
+        .collect();
+    reply(ctx, format!("📈 频道排行 ({})", period.name()), zone, rows).await
+}
+
+#[command(slash_command, guild_only)]
+/// Ranks the threads or forum posts of a channel by messages
+pub async fn threads(
+    ctx: Context<'_>,
+    #[description = "Channel or forum the threads belong to"]
+    #[channel_types("Text", "News", "Forum")]
+    channel: GuildChannel,
+    #[description = "Time window, defaults to 7d"] period: Option<Period>,
+) -> Result<(), BotError> {
+    let guild_id = ctx.guild_id().unwrap_or_default();
+    let period = period.unwrap_or(Period::Week);
+    let zone = zone(ctx).await?;
+    let (from, to) = window(period, zone);
+    let rows = ctx
+        .data()
+        .db
+        .message()
+        .get_thread_stats(guild_id, channel.id, from, to)
+        .await?
+        .into_iter()
+        .map(|(thread_id, count)| (thread_id.mention(), count))
+        .collect();
+    reply(
+        ctx,
+        format!("🧵 {} 的子区排行 ({})", channel.name, period.name()),
+        zone,
+        rows,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_window_covers_local_days() {
+        let zone = Zone::Iana(chrono_tz::Asia::Shanghai);
+        let (from, to) = window(Period::Week, zone);
+        let (from, to) = (from.unwrap(), to.unwrap());
+        assert_eq!(from.format("%H:%M %:z").to_string(), "00:00 +08:00");
+        assert_eq!(to - from, Duration::days(7));
+        assert_eq!(to.date_naive(), zone.today() + Days::new(1));
+        assert!(from <= zone.now() && zone.now() < to);
+        assert_eq!(window(Period::All, zone), (None, None));
+    }
+}
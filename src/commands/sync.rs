@@ -0,0 +1,300 @@
+use poise::{ChoiceParameter, command};
+use serenity::{
+    all::{Command, CreateCommand, GuildId, Http},
+    json::{StaticNode, Value, ValueAsScalar},
+};
+use snafu::ResultExt;
+use tracing::info;
+
+use super::{Context, Data};
+use crate::error::BotError;
+
+/// Where a set of application commands lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Global => write!(f, "global"),
+            Scope::Guild(guild) => write!(f, "guild {guild}"),
+        }
+    }
+}
+
+/// Names of commands that differ between what we serve and what Discord has
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Values Discord omits or fills in when they are left at their default
+fn is_default(value: &Value) -> bool {
+    match value {
+        Value::Static(StaticNode::Null | StaticNode::Bool(false)) => true,
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether every field set in `desired` is present in `existing`
+///
+/// Discord echoes back ids, versions and defaults we never send, so only the fields of the
+/// builder are compared.
+fn matches(desired: &Value, existing: Option<&Value>) -> bool {
+    match (desired, existing) {
+        (desired, None) => is_default(desired),
+        (Value::Object(desired), Some(Value::Object(existing))) => desired
+            .iter()
+            .all(|(key, value)| matches(value, existing.get(key))),
+        (Value::Array(desired), Some(Value::Array(existing))) => {
+            desired.len() == existing.len()
+                && desired
+                    .iter()
+                    .zip(existing)
+                    .all(|(d, e)| matches(d, Some(e)))
+        }
+        (desired, Some(existing)) => {
+            desired == existing || (is_default(desired) && is_default(existing))
+        }
+    }
+}
+
+fn name(value: &Value) -> String {
+    match value {
+        Value::Object(o) => o.get("name").and_then(|n| n.as_str()),
+        _ => None,
+    }
+    .unwrap_or_default()
+    .to_owned()
+}
+
+/// Compare serialized builders against serialized registered commands
+fn diff(desired: &[Value], existing: &[Value]) -> Diff {
+    let mut diff = Diff::default();
+    for command in desired {
+        match existing.iter().find(|e| name(e) == name(command)) {
+            None => diff.added.push(name(command)),
+            Some(e) if !matches(command, Some(e)) => diff.changed.push(name(command)),
+            Some(_) => {}
+        }
+    }
+    diff.removed = existing
+        .iter()
+        .map(name)
+        .filter(|n| !desired.iter().any(|d| &name(d) == n))
+        .collect();
+    diff
+}
+
+fn to_values<T: serde::Serialize>(items: &[T]) -> Result<Vec<Value>, BotError> {
+    items
+        .iter()
+        .map(serenity::json::to_value)
+        .collect::<Result<_, _>>()
+        .whatever_context("Failed to serialize application commands")
+}
+
+async fn registered(http: &Http, scope: Scope) -> Result<Vec<Command>, BotError> {
+    Ok(match scope {
+        Scope::Global => Command::get_global_commands(http).await?,
+        Scope::Guild(guild) => guild.get_commands(http).await?,
+    })
+}
+
+async fn overwrite(
+    http: &Http,
+    scope: Scope,
+    commands: Vec<CreateCommand>,
+) -> Result<Vec<Command>, BotError> {
+    Ok(match scope {
+        Scope::Global => Command::set_global_commands(http, commands).await?,
+        Scope::Guild(guild) => guild.set_commands(http, commands).await?,
+    })
+}
+
+/// Push `commands` to `scope` only if they differ from what is registered
+pub async fn sync(
+    http: &Http,
+    commands: &[poise::Command<Data, BotError>],
+    scope: Scope,
+) -> Result<Diff, BotError> {
+    let desired = poise::builtins::create_application_commands(commands);
+    let diff = diff(
+        &to_values(&desired)?,
+        &to_values(&registered(http, scope).await?)?,
+    );
+    if diff.is_empty() {
+        info!(%scope, "Application commands are up to date");
+    } else {
+        overwrite(http, scope, desired).await?;
+        info!(
+            %scope,
+            added = ?diff.added,
+            changed = ?diff.changed,
+            removed = ?diff.removed,
+            "Application commands updated"
+        );
+    }
+    Ok(diff)
+}
+
+/// Every scope to sync, global included even with dev guilds so commands left there get removed
+pub fn scopes(dev_guild_ids: &[GuildId]) -> Vec<Scope> {
+    let mut scopes = vec![Scope::Global];
+    scopes.extend(dev_guild_ids.iter().copied().map(Scope::Guild));
+    scopes
+}
+
+/// Commands to register in `scope`, none globally while dev guilds are configured
+pub fn served<'a, T>(commands: &'a [T], scope: Scope, dev_guild_ids: &[GuildId]) -> &'a [T] {
+    match scope {
+        Scope::Global if !dev_guild_ids.is_empty() => &[],
+        _ => commands,
+    }
+}
+
+#[command(
+    slash_command,
+    rename = "commands",
+    subcommands("sync_now", "unregister"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR",
+    owners_only,
+    ephemeral
+)]
+/// Manage the registered application commands
+pub async fn manage_commands(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, rename = "sync", owners_only, ephemeral)]
+/// Push command changes to Discord now
+pub async fn sync_now(ctx: Context<'_>) -> Result<(), BotError> {
+    let dev_guild_ids = ctx.data().cfg.load().dev_guild_ids.to_owned();
+    let mut lines = Vec::new();
+    let commands = &ctx.framework().options().commands;
+    for scope in scopes(&dev_guild_ids) {
+        let diff = sync(ctx.http(), served(commands, scope, &dev_guild_ids), scope).await?;
+        lines.push(if diff.is_empty() {
+            format!("✅ {scope}: up to date")
+        } else {
+            format!(
+                "🔄 {scope}: +{} ~{} -{}",
+                diff.added.len(),
+                diff.changed.len(),
+                diff.removed.len()
+            )
+        });
+    }
+    ctx.say(lines.join("\n")).await?;
+    Ok(())
+}
+
+#[derive(Debug, ChoiceParameter)]
+enum UnregisterScope {
+    #[name = "global"]
+    Global,
+    #[name = "this guild"]
+    Guild,
+}
+
+#[command(slash_command, owners_only, ephemeral)]
+/// Remove every application command from a scope
+pub async fn unregister(
+    ctx: Context<'_>,
+    #[description = "Which commands to remove"] scope: UnregisterScope,
+) -> Result<(), BotError> {
+    let scope = match scope {
+        UnregisterScope::Global => Scope::Global,
+        UnregisterScope::Guild => match ctx.guild_id() {
+            Some(guild) => Scope::Guild(guild),
+            None => {
+                ctx.say("❌ Not in a guild").await?;
+                return Ok(());
+            }
+        },
+    };
+    let removed = registered(ctx.http(), scope).await?.len();
+    overwrite(ctx.http(), scope, Vec::new()).await?;
+    info!(%scope, removed, "Application commands unregistered");
+    ctx.say(format!("🗑️ Removed {removed} {scope} commands"))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serenity::json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_ignores_server_fields_and_defaults() {
+        let desired = vec![
+            json!({
+                "name": "ping",
+                "description": "Pong",
+                "name_localizations": {},
+                "options": [],
+                "nsfw": false,
+                "type": 1,
+                "default_member_permissions": "8"
+            }),
+            json!({"name": "timezone", "description": "Set it", "options": [
+                {"name": "zone", "type": 3, "required": true, "autocomplete": true}
+            ]}),
+            json!({"name": "new", "description": "Brand new"}),
+        ];
+        let existing = vec![
+            json!({
+                "id": "1",
+                "application_id": "2",
+                "version": "3",
+                "name": "ping",
+                "description": "Pong",
+                "name_localizations": null,
+                "type": 1,
+                "default_member_permissions": "8",
+                "integration_types": [0]
+            }),
+            json!({"id": "4", "name": "timezone", "description": "Set it", "options": [
+                {"name": "zone", "type": 3, "required": false, "autocomplete": true}
+            ]}),
+            json!({"id": "5", "name": "old", "description": "Gone"}),
+        ];
+        assert_eq!(
+            diff(&desired, &existing),
+            Diff {
+                added: vec!["new".to_string()],
+                changed: vec!["timezone".to_string()],
+                removed: vec!["old".to_string()],
+            }
+        );
+        assert!(diff(&desired[..1], &existing[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_dev_guilds_empty_the_global_scope() {
+        let commands = ["ping", "timezone"];
+        assert_eq!(scopes(&[]), vec![Scope::Global]);
+        assert_eq!(served(&commands, Scope::Global, &[]), &commands);
+
+        let dev = [GuildId::new(1)];
+        assert_eq!(scopes(&dev), vec![Scope::Global, Scope::Guild(dev[0])]);
+        assert!(served(&commands, Scope::Global, &dev).is_empty());
+        assert_eq!(served(&commands, Scope::Guild(dev[0]), &dev), &commands);
+    }
+}
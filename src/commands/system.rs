@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use futures::{StreamExt, stream::FuturesOrdered};
 use poise::{CreateReply, command};
 use serenity::all::{
@@ -6,8 +8,34 @@ use serenity::all::{
 };
 use sysinfo::System;
 
-use super::Context;
-use crate::error::BotError;
+use super::{
+    Context, pages,
+    server::{bot_member, guild_permissions},
+};
+use crate::{
+    error::BotError,
+    sampler::{RETENTION, Sample, Sampler, Summary},
+    utils::sparkline,
+};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const SPARKLINE_POINTS: usize = 48;
+
+/// Min/avg/max of a sampled value with a sparkline of the window
+fn trend(
+    sampler: &Sampler,
+    window: Duration,
+    unit: &str,
+    f: impl Fn(&Sample) -> Option<f64> + Copy,
+) -> String {
+    match sampler.summary(window, f) {
+        Some(Summary { min, avg, max }) => format!(
+            "{min:.1}{unit} / {avg:.1}{unit} / {max:.1}{unit}\n`{}`",
+            sparkline(&sampler.series(window, SPARKLINE_POINTS, f))
+        ),
+        None => "暂无数据".to_string(),
+    }
+}
 
 #[command(
     slash_command,
@@ -27,9 +55,20 @@ pub async fn system_info(ctx: Context<'_>, ephemeral: Option<bool>) -> Result<()
     e.advance()?;
     let allocated_value = allocated.read()?;
     let allocated_mb = allocated_value / 1024 / 1024; // Convert to MB
-    let sys = System::new_all();
+    let sampler = &ctx.data().sampler;
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.refresh_cpu_usage();
     let cpu = sys.cpus().len().to_string();
-    let cpu_usage = sys.global_cpu_usage();
+    // CPU usage is only meaningful relative to a previous refresh
+    let cpu_usage = match sampler.latest() {
+        Some(sample) => sample.cpu_usage,
+        None => {
+            tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+            sys.refresh_cpu_usage();
+            sys.global_cpu_usage()
+        }
+    };
     let total_memory = sys.total_memory() / 1024 / 1024; // Convert to MB
     let used_memory = sys.used_memory() / 1024 / 1024; // Convert to MB
     let memory_usage = (used_memory as f64 / total_memory as f64) * 100.0;
@@ -84,6 +123,31 @@ pub async fn system_info(ctx: Context<'_>, ephemeral: Option<bool>) -> Result<()
         .field("👥 缓存用户数", cached_users.to_string(), true)
         .field("🌐 缓存服务器数", cached_guilds.to_string(), true)
         .field("📺 缓存频道数", cached_channels.to_string(), true)
+        // trends, min / avg / max
+        .field(
+            "📈 CPU 使用率 (1h)",
+            trend(sampler, HOUR, "%", |s| Some(s.cpu_usage as f64)),
+            false,
+        )
+        .field(
+            "📈 CPU 使用率 (24h)",
+            trend(sampler, RETENTION, "%", |s| Some(s.cpu_usage as f64)),
+            false,
+        )
+        .field(
+            "📈 Bot 内存 (24h)",
+            trend(sampler, RETENTION, " MB", |s| {
+                Some(s.allocated as f64 / 1024.0 / 1024.0)
+            }),
+            false,
+        )
+        .field(
+            "📈 WebSocket 延迟 (24h)",
+            trend(sampler, RETENTION, " ms", |s| {
+                s.gateway_latency.map(|d| d.as_secs_f64() * 1000.0)
+            }),
+            false,
+        )
         .thumbnail(ctx.cache().current_user().avatar_url().unwrap_or_default())
         .timestamp(chrono::Utc::now())
         .footer(CreateEmbedFooter::new("系统监控"))
@@ -103,44 +167,47 @@ pub async fn system_info(ctx: Context<'_>, ephemeral: Option<bool>) -> Result<()
     owners_only,
     ephemeral
 )]
+/// Lists the guilds the bot is in with its server-wide permissions
 pub async fn guilds_info(ctx: Context<'_>) -> Result<(), BotError> {
+    ctx.defer_ephemeral().await?;
     let guild_ids = ctx.cache().guilds();
-    // print guilds info, and bot permissions in each guild
-    let message = guild_ids
+    // Guilds that fail are listed with the reason instead of being left out
+    let sections = guild_ids
         .into_iter()
         .map(async |guild_id| {
-            let guild = ctx.cache().guild(guild_id).map(|g| g.to_owned())?;
-            let user_id = ctx.cache().current_user().id;
-            let member = guild.member(ctx, user_id).await.ok()?;
-            let permissions =
-                guild.user_permissions_in(guild.default_channel(member.user.id)?, &member);
-
+            let member = bot_member(ctx, guild_id).await;
+            let guild = ctx.cache().guild(guild_id)?.to_owned();
+            let permissions = match member {
+                Ok(member) => {
+                    let permissions = guild_permissions(&guild, &member);
+                    if permissions.is_all() {
+                        "Administrator".to_string()
+                    } else {
+                        permissions.get_permission_names().join(", ")
+                    }
+                }
+                Err(e) => format!("⚠️ 无法获取 Bot 成员信息: {e}"),
+            };
             Some(format!(
-                "Guild: {}\nPermissions: {}\n\n",
-                guild.name,
-                permissions.get_permission_names().join(", ")
+                "**{}** `{guild_id}`\n成员: {}\n权限: {permissions}\n",
+                guild.name, guild.member_count
             ))
         })
         .collect::<FuturesOrdered<_>>()
         .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>()
-        .join("\n");
+        .await;
+    let uncached = sections.iter().filter(|s| s.is_none()).count();
 
-    if message.is_empty() {
+    if sections.is_empty() {
         ctx.say("没有找到任何服务器信息。").await?;
         return Ok(());
     }
-    ctx.send(
-        CreateReply::default().embed(
-            CreateEmbed::new()
-                .title("Guilds Information")
-                .description(message)
-                .color(0x00FF00),
-        ),
-    )
-    .await?;
-    Ok(())
+    let mut embed = CreateEmbed::new()
+        .title(format!("🌐 服务器列表 ({})", sections.len()))
+        .color(GREEN);
+    if uncached > 0 {
+        embed = embed.field("⚠️ 未缓存", uncached.to_string(), true);
+    }
+    let pages = pages::split(sections.into_iter().flatten(), pages::PAGE_LEN);
+    pages::send(ctx, embed, &pages).await
 }
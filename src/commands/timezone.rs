@@ -0,0 +1,134 @@
+use chrono_tz::{TZ_VARIANTS, Tz};
+use poise::command;
+use serenity::all::MessageBuilder;
+use snafu::whatever;
+
+use super::{Context, zone};
+use crate::error::BotError;
+
+async fn autocomplete_tz<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = &'static str> + 'a {
+    let partial = partial.to_lowercase();
+    TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter(move |name| name.to_lowercase().contains(&partial))
+        .take(25)
+}
+
+fn parse_tz(name: &str) -> Result<Tz, BotError> {
+    match name.parse() {
+        Ok(tz) => Ok(tz),
+        Err(_) => whatever!("Unknown time zone `{name}`"),
+    }
+}
+
+#[command(
+    slash_command,
+    subcommands("set", "guild", "clear", "clear_guild", "show"),
+    subcommand_required,
+    name_localized("zh-CN", "时区"),
+    description_localized("zh-CN", "设置显示时间和统计日期使用的时区"),
+    ephemeral
+)]
+/// Manages the time zone used for dates and statistics
+pub async fn timezone(_: Context<'_>) -> Result<(), BotError> {
+    Ok(())
+}
+
+#[command(slash_command, ephemeral)]
+/// Sets your own time zone
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "IANA time zone, e.g. Asia/Shanghai"]
+    #[autocomplete = "autocomplete_tz"]
+    zone: String,
+) -> Result<(), BotError> {
+    let tz = parse_tz(&zone)?;
+    ctx.data()
+        .db
+        .timezone()
+        .set_user(ctx.author().id, tz)
+        .await?;
+    ctx.say(
+        MessageBuilder::new()
+            .push("✅ Your time zone is now ")
+            .push_mono_safe(tz.name())
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+/// Sets the default time zone of this server
+pub async fn guild(
+    ctx: Context<'_>,
+    #[description = "IANA time zone, e.g. Asia/Shanghai"]
+    #[autocomplete = "autocomplete_tz"]
+    zone: String,
+) -> Result<(), BotError> {
+    let tz = parse_tz(&zone)?;
+    let Some(guild_id) = ctx.guild_id() else {
+        whatever!("Not in a guild");
+    };
+    ctx.data().db.timezone().set_guild(guild_id, tz).await?;
+    ctx.say(
+        MessageBuilder::new()
+            .push("✅ Server time zone is now ")
+            .push_mono_safe(tz.name())
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
+
+#[command(slash_command, ephemeral)]
+/// Removes your own time zone
+pub async fn clear(ctx: Context<'_>) -> Result<(), BotError> {
+    ctx.data().db.timezone().clear_user(ctx.author().id).await?;
+    ctx.say("✅ Your time zone has been cleared").await?;
+    Ok(())
+}
+
+#[command(
+    slash_command,
+    rename = "clear-guild",
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    ephemeral
+)]
+/// Removes the default time zone of this server
+pub async fn clear_guild(ctx: Context<'_>) -> Result<(), BotError> {
+    let Some(guild_id) = ctx.guild_id() else {
+        whatever!("Not in a guild");
+    };
+    ctx.data().db.timezone().clear_guild(guild_id).await?;
+    ctx.say("✅ Server time zone has been cleared").await?;
+    Ok(())
+}
+
+#[command(slash_command, ephemeral)]
+/// Shows the time zone in effect for you
+pub async fn show(ctx: Context<'_>) -> Result<(), BotError> {
+    let zone = zone(ctx).await?;
+    ctx.say(
+        MessageBuilder::new()
+            .push("🕒 ")
+            .push_mono_safe(zone.to_string())
+            .push(format!(
+                "\nLocal time: {}",
+                zone.format(chrono::Utc::now(), "%Y-%m-%d %H:%M:%S %Z")
+            ))
+            .build(),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,524 @@
+mod secret;
+mod sources;
+mod validate;
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arc_swap::ArcSwap;
+use chrono::FixedOffset;
+use chrono_tz::Tz;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+use serenity::{all::*, prelude::TypeMapKey};
+use snafu::{OptionExt, ResultExt, whatever};
+
+pub use self::{
+    secret::Secret,
+    sources::{ENV_PREFIX, FileFormat, OVERLAY_DIR},
+    validate::FieldError,
+};
+use crate::{error::BotError, utils::Zone};
+
+#[serde_as]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BotCfg {
+    /// Fixed UTC offset in seconds, used when `timezone` is unset
+    #[serde(default)]
+    pub time_offset: i32,
+    /// IANA time zone name, e.g. `Asia/Shanghai`
+    #[schemars(with = "Option<String>")]
+    pub timezone: Option<Tz>,
+    /// Discord bot token, prefer `tokenFile` or `DOG_BOT_TOKEN` to keep it out of this file
+    #[serde(default, skip_serializing_if = "Secret::is_external")]
+    #[schemars(with = "String")]
+    pub token: Secret<String>,
+    /// File holding the bot token, relative paths are resolved against
+    /// `$CREDENTIALS_DIRECTORY` when systemd provides one
+    pub token_file: Option<PathBuf>,
+    /// Address of the embedded HTTP server, disabled when unset
+    pub http_addr: Option<SocketAddr>,
+    /// Persist system samples to the database so trends survive restarts
+    #[serde(default)]
+    pub persist_samples: bool,
+    /// Log output sinks
+    #[serde(default)]
+    pub logging: LogCfg,
+    /// Log filter directive, overrides `RUST_LOG` and is re-applied on reload
+    pub log_level: Option<String>,
+    /// Register commands in these guilds instead of globally, updates show up instantly
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub dev_guild_ids: Vec<GuildId>,
+    /// `sqlite://` or `postgres://` URL, overrides `--db` when set
+    #[serde(default, skip_serializing_if = "Secret::is_unset_or_external")]
+    #[schemars(with = "Option<String>")]
+    pub database_url: Option<Secret<String>>,
+    /// Connection pool and SQLite tuning
+    #[serde(default)]
+    pub database: DatabaseCfg,
+    /// Scheduled database backups, disabled when unset
+    pub backup: Option<BackupCfg>,
+    /// Activity recorded alongside each message
+    #[serde(default)]
+    pub tracking: TrackingCfg,
+    /// Users allowed to run moderation commands in every guild
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub extra_admin_user_ids: Vec<UserId>,
+    /// Members with any of these roles may run moderation commands
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub admin_role_ids: Vec<RoleId>,
+    /// Moderation commands and case records
+    #[serde(default)]
+    pub moderation: ModerationCfg,
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// Whether `token` was read from `tokenFile` rather than set alongside it
+    #[serde(skip)]
+    token_from_file: bool,
+}
+
+/// Output format of a log sink
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    /// Human readable, colored when writing to a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+/// How often the log file is rolled over
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogCfg {
+    /// Format of the stdout sink
+    pub format: LogFormat,
+    /// Optional rolling file sink
+    pub file: Option<LogFileCfg>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFileCfg {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub format: LogFormat,
+    pub rotation: LogRotation,
+    /// Number of rotated files to keep, unlimited when unset
+    pub max_files: Option<usize>,
+}
+
+impl Default for LogFileCfg {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("logs"),
+            prefix: "dog-bot.log".to_string(),
+            format: LogFormat::Json,
+            rotation: LogRotation::Daily,
+            max_files: Some(7),
+        }
+    }
+}
+
+/// Level at which sqlx logs every executed statement
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryLogLevel {
+    #[default]
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<QueryLogLevel> for log::LevelFilter {
+    fn from(level: QueryLogLevel) -> Self {
+        match level {
+            QueryLogLevel::Off => log::LevelFilter::Off,
+            QueryLogLevel::Error => log::LevelFilter::Error,
+            QueryLogLevel::Warn => log::LevelFilter::Warn,
+            QueryLogLevel::Info => log::LevelFilter::Info,
+            QueryLogLevel::Debug => log::LevelFilter::Debug,
+            QueryLogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Connection pool settings
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DatabaseCfg {
+    /// Maximum number of pooled connections
+    pub max_connections: u32,
+    /// Seconds to wait for a free connection before failing
+    pub acquire_timeout_secs: u64,
+    /// Milliseconds a connection waits on a locked database before failing
+    pub busy_timeout_ms: u64,
+    pub log_level: QueryLogLevel,
+}
+
+impl Default for DatabaseCfg {
+    fn default() -> Self {
+        Self {
+            max_connections: 8,
+            acquire_timeout_secs: 30,
+            busy_timeout_ms: 5000,
+            log_level: QueryLogLevel::Off,
+        }
+    }
+}
+
+/// Where and how often the database is copied
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupCfg {
+    pub directory: PathBuf,
+    /// Hours between scheduled backups
+    pub interval_hours: u64,
+    /// Number of backups to keep, the oldest are deleted first
+    pub keep: usize,
+}
+
+impl Default for BackupCfg {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("backups"),
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+/// Optional message activity, each kind can be turned off to save space
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrackingCfg {
+    /// Store every guild message for `/stats`, the other kinds only apply when this is on
+    pub messages: bool,
+    /// Count edits and remember when a message was last edited
+    pub edits: bool,
+    /// Record who reacted to whose messages
+    pub reactions: bool,
+    /// Count the attachments of each message
+    pub attachments: bool,
+    /// Remember the parent channel of messages in threads and forum posts
+    pub threads: bool,
+}
+
+impl Default for TrackingCfg {
+    fn default() -> Self {
+        Self {
+            messages: false,
+            edits: true,
+            reactions: true,
+            attachments: true,
+            threads: true,
+        }
+    }
+}
+
+/// Where moderation actions are announced and whether targets hear about them
+#[serde_as]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModerationCfg {
+    /// Mod-log channel of each guild, keyed by guild id
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[schemars(with = "HashMap<String, String>")]
+    pub log_channels: HashMap<GuildId, ChannelId>,
+    /// Send the target a DM describing the action and reason
+    pub dm_targets: bool,
+    /// Anti-raid thresholds of each guild, keyed by guild id, joins elsewhere are not tracked
+    #[serde_as(as = "HashMap<DisplayFromStr, _>")]
+    #[schemars(with = "HashMap<String, RaidCfg>")]
+    pub raid: HashMap<GuildId, RaidCfg>,
+}
+
+impl Default for ModerationCfg {
+    fn default() -> Self {
+        Self {
+            log_channels: HashMap::new(),
+            dm_targets: true,
+            raid: HashMap::new(),
+        }
+    }
+}
+
+/// When a burst of joins counts as a raid and what a lockdown changes
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RaidCfg {
+    /// Joins within the window that count as a raid
+    pub joins: u32,
+    /// Joins of new accounts within the window that count as a raid
+    pub new_accounts: u32,
+    /// Accounts younger than this many days are new
+    pub account_age_days: u32,
+    /// Length of the sliding window in seconds
+    pub window_secs: u64,
+    /// Lock the guild down as soon as a raid is detected, otherwise only alert
+    pub lockdown: bool,
+    /// Slowmode of text channels during a lockdown, 0 leaves channels alone
+    pub slowmode_secs: u16,
+}
+
+impl Default for RaidCfg {
+    fn default() -> Self {
+        Self {
+            joins: 10,
+            new_accounts: 5,
+            account_age_days: 7,
+            window_secs: 60,
+            lockdown: false,
+            slowmode_secs: 30,
+        }
+    }
+}
+
+impl TypeMapKey for BotCfg {
+    type Value = Arc<ArcSwap<BotCfg>>;
+}
+
+#[allow(dead_code)]
+pub(crate) trait GetCfg {
+    async fn cfg(&self) -> Result<Arc<ArcSwap<BotCfg>>, BotError>;
+}
+
+impl GetCfg for Context {
+    async fn cfg(&self) -> Result<Arc<ArcSwap<BotCfg>>, BotError> {
+        self.data
+            .read()
+            .await
+            .get::<BotCfg>()
+            .cloned()
+            .whatever_context("Failed to get bot configuration from type map")
+    }
+}
+
+impl BotCfg {
+    /// Read and validate the configuration
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, BotError> {
+        let cfg = Self::load(path)?;
+        let errors = cfg.validate();
+        if !errors.is_empty() {
+            whatever!(
+                "Invalid configuration in {}:\n{}",
+                cfg.path.display(),
+                errors
+                    .iter()
+                    .map(|e| format!("  - {e}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Ok(cfg)
+    }
+
+    /// Read the configuration without validating it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BotError> {
+        let figment = sources::figment(path.as_ref())?;
+        let mut cfg = Self {
+            path: path.as_ref().to_owned(),
+            ..figment
+                .extract_lossy()
+                .whatever_context::<&str, BotError>("Failed to read bot configuration")?
+        };
+        // only secrets from the main file may be written back to it
+        let main = path.as_ref().canonicalize().ok();
+        let from_main = |key: &str| {
+            figment
+                .find_metadata(key)
+                .and_then(|m| m.source.as_ref())
+                .and_then(|s| s.file_path())
+                .is_some_and(|p| p.canonicalize().ok() == main)
+        };
+        if !from_main("token") {
+            cfg.token.mark_external();
+        }
+        if let Some(url) = cfg.database_url.as_mut()
+            && !from_main("databaseUrl")
+        {
+            url.mark_external();
+        }
+        // both set is reported by `validate`, the file is only read when it's the sole source
+        if let Some(file) = &cfg.token_file
+            && cfg.token.expose().trim().is_empty()
+        {
+            cfg.token = Secret::external(read_token_file(file)?);
+            cfg.token_from_file = true;
+        }
+        Ok(cfg)
+    }
+
+    /// JSON Schema of the configuration file, for editor completion
+    pub fn schema() -> Result<String, BotError> {
+        serenity::json::to_string_pretty(&schemars::schema_for!(BotCfg))
+            .whatever_context("Failed to serialize configuration schema")
+    }
+
+    /// The configured zone, preferring `timezone` over `timeOffset`
+    pub fn zone(&self) -> Zone {
+        match self.timezone {
+            Some(tz) => Zone::Iana(tz),
+            None => FixedOffset::east_opt(self.time_offset).map_or_else(Zone::default, Zone::Fixed),
+        }
+    }
+
+    /// Re-read the configuration from the path it was loaded from
+    pub fn reload(cfg: &ArcSwap<BotCfg>) -> Result<Arc<BotCfg>, BotError> {
+        let new = Arc::new(Self::read(&cfg.load().path)?);
+        cfg.store(new.to_owned());
+        Ok(new)
+    }
+
+    /// Write the configuration back in the format of its main file
+    ///
+    /// Keys set by overlays or the environment keep the main file's value, secrets from
+    /// elsewhere are left out.
+    pub fn write(&self) -> Result<(), BotError> {
+        let format = FileFormat::of(&self.path).unwrap_or(FileFormat::Json);
+        let value = sources::main_only(&self.path, self)?;
+        std::fs::write(&self.path, format.serialize(&value)?)
+            .whatever_context("Failed to write configuration file")
+    }
+}
+
+/// Read a credential file the way Docker secrets and systemd `LoadCredential=` provide them
+fn read_token_file(file: &Path) -> Result<String, BotError> {
+    let path = match std::env::var_os("CREDENTIALS_DIRECTORY") {
+        Some(dir) if file.is_relative() => Path::new(&dir).join(file),
+        _ => file.to_owned(),
+    };
+    let token =
+        std::fs::read_to_string(&path).with_whatever_context::<_, String, BotError>(|_| {
+            format!("Failed to read tokenFile {}", path.display())
+        })?;
+    Ok(token.trim().to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_schema_is_up_to_date() {
+        assert_eq!(
+            BotCfg::schema().unwrap().trim_end(),
+            include_str!("../../config.schema.json").trim_end(),
+            "run `cargo run -- print-schema > config.schema.json`"
+        );
+    }
+
+    #[test]
+    fn test_moderation_log_channels_keyed_by_guild() {
+        let dir = std::env::temp_dir().join(format!("dog-bot-mod-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "timeOffset = 0\nadminRoleIds = [\"3\"]\n[moderation.logChannels]\n1 = \"2\"\n\
+             [moderation.raid.1]\njoins = 5\nlockdown = true\n",
+        )
+        .unwrap();
+        let cfg = BotCfg::load(&path).unwrap();
+        std::fs::remove_dir_all(dir).ok();
+        assert_eq!(
+            cfg.moderation.log_channels.get(&GuildId::new(1)),
+            Some(&ChannelId::new(2))
+        );
+        assert_eq!(cfg.admin_role_ids, vec![RoleId::new(3)]);
+        assert!(cfg.moderation.dm_targets);
+        assert_eq!(
+            cfg.moderation.raid.get(&GuildId::new(1)),
+            Some(&RaidCfg {
+                joins: 5,
+                lockdown: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_timezone_without_offset() {
+        let dir = std::env::temp_dir().join(format!("dog-bot-tz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        std::fs::write(&path, r#"{"timezone": "Asia/Shanghai"}"#).unwrap();
+        let cfg = BotCfg::load(&path).unwrap();
+        std::fs::remove_dir_all(dir).ok();
+        assert_eq!(cfg.time_offset, 0);
+        assert_eq!(cfg.zone(), Zone::Iana(chrono_tz::Asia::Shanghai));
+    }
+
+    #[test]
+    fn test_token_file_is_not_written_back() {
+        let dir = std::env::temp_dir().join(format!("dog-bot-cfg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let token_path = dir.join("token");
+        std::fs::write(&token_path, "file.token.value\n").unwrap();
+        let path = dir.join("config.json");
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"timeOffset": 0, "tokenFile": {:?}}}"#,
+                token_path.display().to_string()
+            ),
+        )
+        .unwrap();
+
+        let cfg = BotCfg::load(&path).unwrap();
+        assert_eq!(cfg.token.expose(), "file.token.value");
+        assert!(!format!("{cfg:?}").contains("file.token.value"));
+        cfg.write().unwrap();
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("file.token.value")
+        );
+
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"timeOffset": 0, "token": "inline.token.value", "tokenFile": {:?}}}"#,
+                token_path.display().to_string()
+            ),
+        )
+        .unwrap();
+        let cfg = BotCfg::load(&path).unwrap();
+        assert_eq!(cfg.token.expose(), "inline.token.value");
+        assert_eq!(cfg.validate()[0].path, "tokenFile");
+
+        std::fs::write(&path, r#"{"timeOffset": 0, "token": "inline.token.value"}"#).unwrap();
+        let cfg = BotCfg::load(&path).unwrap();
+        assert!(!cfg.token.is_external());
+        cfg.write().unwrap();
+        assert!(
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("inline.token.value")
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A sensitive value that never shows up in `Debug` output or logs
+///
+/// Values read from the environment or a file are marked external so `BotCfg::write` doesn't
+/// copy them into the configuration file.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T> {
+    value: T,
+    external: bool,
+}
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            external: false,
+        }
+    }
+
+    /// A value that came from outside the configuration file
+    pub fn external(value: T) -> Self {
+        Self {
+            value,
+            external: true,
+        }
+    }
+
+    /// The wrapped value, only call this where it is actually needed
+    pub fn expose(&self) -> &T {
+        &self.value
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+
+    /// `skip_serializing_if` for optional secrets
+    pub fn is_unset_or_external(secret: &Option<Self>) -> bool {
+        secret.as_ref().is_none_or(Self::is_external)
+    }
+
+    pub(super) fn mark_external(&mut self) {
+        self.external = true;
+    }
+}
+
+impl<T> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl<T: Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::new("hunter2".to_string());
+        assert_eq!(format!("{secret:?}"), "Secret([redacted])");
+        assert!(!format!("{:?}", Some(&secret)).contains("hunter2"));
+        assert_eq!(
+            serenity::json::to_string(&secret).unwrap(),
+            "\"hunter2\"".to_string()
+        );
+        assert!(Secret::external(String::new()).is_external());
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::{Path, PathBuf};
+
+use figment::{
+    Figment,
+    providers::{Env, Format, Json, Toml, Yaml},
+    value::{Dict, Value},
+};
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
+
+use crate::error::BotError;
+
+/// Prefix of environment overrides, `__` separates nested keys
+pub const ENV_PREFIX: &str = "DOG_BOT_";
+/// Directory of overlay files next to the main configuration file
+pub const OVERLAY_DIR: &str = "conf.d";
+
+/// Configuration file formats, chosen by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl FileFormat {
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn provide(self, figment: Figment, path: &Path) -> Figment {
+        match self {
+            Self::Json => figment.merge(Json::file_exact(path)),
+            Self::Toml => figment.merge(Toml::file_exact(path)),
+            Self::Yaml => figment.merge(Yaml::file_exact(path)),
+        }
+    }
+
+    pub fn serialize(self, value: &impl Serialize) -> Result<String, BotError> {
+        match self {
+            Self::Json => serenity::json::to_string_pretty(value)
+                .whatever_context("Failed to serialize configuration to JSON"),
+            Self::Toml => toml::to_string_pretty(value)
+                .whatever_context("Failed to serialize configuration to TOML"),
+            Self::Yaml => serde_yaml::to_string(value)
+                .whatever_context("Failed to serialize configuration to YAML"),
+        }
+    }
+}
+
+fn format_of(path: &Path) -> Result<FileFormat, BotError> {
+    FileFormat::of(path).with_whatever_context::<_, String, BotError>(|| {
+        format!(
+            "Unsupported configuration file {}, expected .json, .toml, .yaml or .yml",
+            path.display()
+        )
+    })
+}
+
+/// Overlay files in `conf.d/` next to `path`, in file name order
+fn overlays(path: &Path) -> Result<Vec<PathBuf>, BotError> {
+    let dir = path
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join(OVERLAY_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = std::fs::read_dir(&dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|f| f.is_file() && FileFormat::of(f).is_some());
+    files.sort();
+    Ok(files)
+}
+
+/// `DATABASE__MAX_CONNECTIONS` to `database.maxConnections`
+fn env_key(key: &str) -> String {
+    key.split("__")
+        .map(|segment| {
+            let mut out = String::with_capacity(segment.len());
+            let mut upper = false;
+            for c in segment.chars() {
+                match c {
+                    '_' => upper = !out.is_empty(),
+                    c if upper => {
+                        out.push(c.to_ascii_uppercase());
+                        upper = false;
+                    }
+                    c => out.push(c.to_ascii_lowercase()),
+                }
+            }
+            out
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The main file, then each overlay, then the environment
+pub fn figment(path: &Path) -> Result<Figment, BotError> {
+    let mut figment = format_of(path)?.provide(Figment::new(), path);
+    for overlay in overlays(path)? {
+        figment = format_of(&overlay)?.provide(figment, &overlay);
+    }
+    Ok(figment.merge(
+        Env::prefixed(ENV_PREFIX)
+            .map(|key| env_key(key.as_str()).into())
+            // `map` turns lowercasing back on, which would undo the camelCase keys
+            .lowercase(false),
+    ))
+}
+
+/// `value` with every key an overlay or the environment set put back to the main file's own
+/// value, so writing it doesn't bake those in
+pub fn main_only(path: &Path, value: &impl Serialize) -> Result<Value, BotError> {
+    let value = Value::serialize(value)
+        .whatever_context::<&str, BotError>("Failed to serialize configuration")?;
+    let merged = figment(path)?;
+    let original = format_of(path)?
+        .provide(Figment::new(), path)
+        .extract::<Value>()
+        .ok();
+    let main = path.canonicalize().ok();
+    Ok(
+        restore(value, original.as_ref(), "", &merged, main.as_deref())
+            .unwrap_or_else(|| Dict::new().into()),
+    )
+}
+
+fn restore(
+    value: Value,
+    original: Option<&Value>,
+    key: &str,
+    merged: &Figment,
+    main: Option<&Path>,
+) -> Option<Value> {
+    match value {
+        Value::Dict(tag, map) if !map.is_empty() => {
+            let map = map
+                .into_iter()
+                .filter_map(|(k, v)| {
+                    let path = match key {
+                        "" => k.to_owned(),
+                        _ => format!("{key}.{k}"),
+                    };
+                    let original = original.and_then(|o| o.as_dict()?.get(&k));
+                    let v = restore(v, original, &path, merged, main)?;
+                    Some((k, v))
+                })
+                .collect::<Dict>();
+            // a table only overlays added goes away with its keys
+            (!map.is_empty() || original.is_some()).then_some(Value::Dict(tag, map))
+        }
+        value => {
+            let foreign = merged.find_metadata(key).is_some_and(|m| {
+                m.source
+                    .as_ref()
+                    .and_then(|s| s.file_path())
+                    .is_none_or(|p| p.canonicalize().ok().as_deref() != main)
+            });
+            let value = if foreign { original?.to_owned() } else { value };
+            // unset options, TOML has no null
+            (!matches!(value, Value::Empty(..))).then_some(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::BotCfg;
+
+    #[test]
+    fn test_env_key() {
+        assert_eq!(env_key("TOKEN"), "token");
+        assert_eq!(env_key("TIME_OFFSET"), "timeOffset");
+        assert_eq!(env_key("LOGGING__FILE__MAX_FILES"), "logging.file.maxFiles");
+        assert_eq!(
+            env_key("DATABASE__MAX_CONNECTIONS"),
+            "database.maxConnections"
+        );
+    }
+
+    #[test]
+    fn test_overlays_merge_in_order() {
+        let dir = std::env::temp_dir().join(format!("dog-bot-overlay-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(OVERLAY_DIR)).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "token = \"a.b.c\"\ntimeOffset = 0\nlogLevel = \"info\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(OVERLAY_DIR).join("10-prod.yaml"),
+            "timeOffset: 3600\nlogLevel: warn\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(OVERLAY_DIR).join("20-local.json"),
+            r#"{"logLevel": "debug"}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join(OVERLAY_DIR).join("README.md"), "ignored").unwrap();
+
+        let cfg = BotCfg::load(&path).unwrap();
+        assert_eq!(cfg.token.expose(), "a.b.c");
+        assert_eq!(cfg.time_offset, 3600);
+        assert_eq!(cfg.log_level.as_deref(), Some("debug"));
+
+        // overlays stay overlays when the configuration is written back
+        cfg.write().unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("logLevel = \"info\""));
+        assert!(written.contains("timeOffset = 0"));
+        assert_eq!(
+            BotCfg::load(&path).unwrap().log_level.as_deref(),
+            Some("debug")
+        );
+
+        assert!(figment(&dir.join("config.ini")).is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+
+use chrono::FixedOffset;
+use serenity::utils::token;
+
+use super::BotCfg;
+use crate::logging::parse_filter;
+
+/// A configuration value that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Path of the offending field, e.g. `logging.file.maxFiles`
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn push(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+}
+
+impl BotCfg {
+    /// Check values that deserialize fine but cannot work at runtime
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Errors::default();
+
+        if self.token.expose().trim().is_empty() {
+            errors.push("token", "is empty, set token, tokenFile or DOG_BOT_TOKEN");
+        } else if token::validate(self.token.expose()).is_err() {
+            errors.push(
+                "token",
+                "does not look like a bot token, expected three `.` separated parts",
+            );
+        }
+
+        if self.token_file.is_some()
+            && !self.token_from_file
+            && !self.token.expose().trim().is_empty()
+        {
+            errors.push(
+                "tokenFile",
+                "conflicts with token, set only one of token, tokenFile or DOG_BOT_TOKEN",
+            );
+        }
+
+        if FixedOffset::east_opt(self.time_offset).is_none() {
+            errors.push(
+                "timeOffset",
+                format!(
+                    "{} is out of range, expected seconds within ±86399",
+                    self.time_offset
+                ),
+            );
+        }
+
+        if let Some(directive) = &self.log_level
+            && let Err(e) = parse_filter(directive)
+        {
+            errors.push("logLevel", e.to_string());
+        }
+
+        if let Some(file) = &self.logging.file {
+            if file.directory.as_os_str().is_empty() {
+                errors.push("logging.file.directory", "is empty");
+            }
+            if file.prefix.is_empty() {
+                errors.push("logging.file.prefix", "is empty");
+            }
+            if file.max_files == Some(0) {
+                errors.push(
+                    "logging.file.maxFiles",
+                    "must be at least 1, omit it to keep every file",
+                );
+            }
+        }
+
+        if let Some(url) = &self.database_url {
+            let url = url.expose();
+            if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+                if !cfg!(feature = "postgres") {
+                    errors.push(
+                        "databaseUrl",
+                        "Postgres requires building with `--features postgres`",
+                    );
+                }
+            } else if !url.starts_with("sqlite:") {
+                errors.push("databaseUrl", "expected a `sqlite://` or `postgres://` URL");
+            }
+        }
+
+        if self.database.max_connections == 0 {
+            errors.push("database.maxConnections", "must be at least 1");
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            errors.push("database.acquireTimeoutSecs", "must be at least 1");
+        }
+
+        if let Some(backup) = &self.backup {
+            if backup.directory.as_os_str().is_empty() {
+                errors.push("backup.directory", "is empty");
+            }
+            if backup.interval_hours == 0 {
+                errors.push("backup.intervalHours", "must be at least 1");
+            }
+            if backup.keep == 0 {
+                errors.push("backup.keep", "must be at least 1");
+            }
+        }
+
+        for (guild_id, raid) in &self.moderation.raid {
+            let path = format!("moderation.raid.{guild_id}");
+            if raid.joins < 2 {
+                errors.push(format!("{path}.joins"), "must be at least 2");
+            }
+            if raid.new_accounts == 0 {
+                errors.push(format!("{path}.newAccounts"), "must be at least 1");
+            }
+            if !(1..=3600).contains(&raid.window_secs) {
+                errors.push(
+                    format!("{path}.windowSecs"),
+                    "must be between 1 and 3600 seconds",
+                );
+            }
+            if raid.slowmode_secs > 21600 {
+                errors.push(
+                    format!("{path}.slowmodeSecs"),
+                    "must be at most 21600, Discord's longest slowmode",
+                );
+            }
+        }
+
+        if let Some(addr) = self.http_addr
+            && addr.port() == 0
+        {
+            errors.push("httpAddr", "port must not be 0");
+        }
+
+        errors.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::{LogFileCfg, Secret};
+
+    fn valid() -> BotCfg {
+        BotCfg {
+            // Only the shape is checked, three `.` separated parts
+            token: Secret::new("test.token.fake".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_field_paths() {
+        assert_eq!(valid().validate(), vec![]);
+
+        let cfg = BotCfg {
+            token: Secret::new("not-a-token".to_string()),
+            time_offset: 90_000,
+            log_level: Some("serenity=loud".to_string()),
+            http_addr: Some("127.0.0.1:0".parse().unwrap()),
+            logging: crate::config::LogCfg {
+                file: Some(LogFileCfg {
+                    max_files: Some(0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            moderation: crate::config::ModerationCfg {
+                raid: [(
+                    serenity::all::GuildId::new(1),
+                    crate::config::RaidCfg {
+                        joins: 1,
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let paths = cfg
+            .validate()
+            .into_iter()
+            .map(|e| e.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "token",
+                "timeOffset",
+                "logLevel",
+                "logging.file.maxFiles",
+                "moderation.raid.1.joins",
+                "httpAddr"
+            ]
+        );
+
+        let empty = BotCfg::default().validate();
+        assert!(empty[0].to_string().starts_with("token: is empty"));
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    fs::{File, TryLockError},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::Utc;
+use sea_orm::{ConnectionTrait, DbBackend, Statement};
+use snafu::{ResultExt, whatever};
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{info, warn};
+
+use super::BotDatabase;
+use crate::{config::BackupCfg, error::BotError};
+
+const PREFIX: &str = "backup-";
+const EXTENSION: &str = "db";
+
+/// A verified copy of the database
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub size: i64,
+}
+
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', "''"))
+}
+
+/// Backups in `dir`, oldest first
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>, BotError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut backups = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    backups.retain(|p| {
+        p.is_file()
+            && p.extension().is_some_and(|e| e == EXTENSION)
+            && p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(PREFIX))
+    });
+    // the timestamp in the name sorts chronologically
+    backups.sort();
+    Ok(backups)
+}
+
+/// Delete all but the newest `keep` backups, returning the deleted paths
+pub fn prune(dir: &Path, keep: usize) -> Result<Vec<PathBuf>, BotError> {
+    let backups = list(dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    let removed = backups.into_iter().take(excess).collect::<Vec<_>>();
+    for path in &removed {
+        std::fs::remove_file(path)?;
+    }
+    Ok(removed)
+}
+
+impl BotDatabase {
+    /// Run `PRAGMA integrity_check`, failing with every reported problem
+    pub async fn integrity_check(&self) -> Result<(), BotError> {
+        let stmt = Statement::from_string(self.db.get_database_backend(), "PRAGMA integrity_check");
+        let problems = self
+            .db
+            .query_all(stmt)
+            .await?
+            .iter()
+            .map(|row| row.try_get_by_index::<String>(0))
+            .collect::<Result<Vec<_>, _>>()?;
+        if problems != ["ok"] {
+            whatever!("Integrity check failed: {}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    /// Write a consistent copy to `path` with `VACUUM INTO` and verify it
+    pub async fn backup_to(&self, path: &Path) -> Result<Backup, BotError> {
+        if self.backend() != DbBackend::Sqlite {
+            whatever!("Built-in backups only support SQLite, use `pg_dump` for Postgres");
+        }
+        if path.exists() {
+            whatever!("Backup {} already exists", path.display());
+        }
+        self.db
+            .execute_unprepared(&format!("VACUUM INTO {}", quote(path)))
+            .await?;
+        let copy = BotDatabase::open_read_only(path).await?;
+        let verified = copy.integrity_check().await;
+        let size = copy.size().await;
+        copy.db.close().await?;
+        if let Err(e) = verified {
+            std::fs::remove_file(path).ok();
+            return Err(e);
+        }
+        Ok(Backup {
+            path: path.to_owned(),
+            size: size?,
+        })
+    }
+
+    /// Write a timestamped backup into `cfg.directory` and apply retention
+    pub async fn backup(&self, cfg: &BackupCfg) -> Result<Backup, BotError> {
+        std::fs::create_dir_all(&cfg.directory)?;
+        let name = format!(
+            "{PREFIX}{}.{EXTENSION}",
+            Utc::now().format("%Y%m%d-%H%M%S%.3f")
+        );
+        let backup = self.backup_to(&cfg.directory.join(name)).await?;
+        for removed in prune(&cfg.directory, cfg.keep)? {
+            info!(path = %removed.display(), "Removed old backup");
+        }
+        Ok(backup)
+    }
+}
+
+/// Back up every `cfg.interval_hours` in the background
+pub fn spawn(db: BotDatabase, cfg: BackupCfg) {
+    tokio::spawn(async move {
+        let period = Duration::from_secs(cfg.interval_hours * 60 * 60);
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            match db.backup(&cfg).await {
+                Ok(backup) => info!(
+                    path = %backup.path.display(),
+                    size = backup.size,
+                    "Database backed up"
+                ),
+                Err(e) => warn!("Scheduled backup failed: {}", e),
+            }
+        }
+    });
+}
+
+fn sibling(db: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{suffix}", db.display()))
+}
+
+/// Claim on a SQLite database held by `run`, the OS drops it when the process exits
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Lock `<db>.lock`, failing while another process holds it
+    pub fn acquire(db: &Path) -> Result<Self, BotError> {
+        let path = sibling(db, ".lock");
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { _file: file }),
+            Err(TryLockError::WouldBlock) => whatever!(
+                "{} is in use by a running bot ({} is locked), stop it first",
+                db.display(),
+                path.display()
+            ),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// Replace the database at `db` with a verified `backup`
+///
+/// Fails while a bot holds the [`RunLock`]. The current file and its WAL are kept next to it
+/// with a `.pre-restore` suffix.
+pub async fn restore(db: &Path, backup: &Path) -> Result<PathBuf, BotError> {
+    // held until the swap is done, so the bot can't start in between either
+    let _lock = RunLock::acquire(db)?;
+    let source = BotDatabase::open_read_only(backup).await?;
+    source
+        .integrity_check()
+        .await
+        .with_whatever_context::<_, String, BotError>(|_| {
+            format!("Refusing to restore {}", backup.display())
+        })?;
+    source.db.close().await?;
+
+    let staged = sibling(db, ".restore");
+    std::fs::copy(backup, &staged)?;
+    let previous = sibling(db, ".pre-restore");
+    // the WAL of a bot that crashed holds commits that never reached the main file, it moves
+    // along so the previous copy opens with them
+    for sidecar in ["-wal", "-shm"] {
+        let moved = sibling(&previous, sidecar);
+        std::fs::remove_file(&moved).ok();
+        if sibling(db, sidecar).exists() {
+            std::fs::rename(sibling(db, sidecar), moved)?;
+        }
+    }
+    if db.exists() {
+        std::fs::rename(db, &previous)?;
+    }
+    std::fs::rename(&staged, db)?;
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono_tz::Asia::{Seoul, Tokyo};
+    use serenity::all::UserId;
+
+    use super::*;
+    use crate::config::DatabaseCfg;
+
+    #[tokio::test]
+    async fn test_backup_retention_and_restore() {
+        let dir = std::env::temp_dir().join(format!("dog-bot-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = BackupCfg {
+            directory: dir.join("backups"),
+            keep: 2,
+            ..Default::default()
+        };
+        std::fs::create_dir_all(&cfg.directory).unwrap();
+        for stamp in ["20260101-000000.000", "20260102-000000.000"] {
+            std::fs::write(cfg.directory.join(format!("{PREFIX}{stamp}.db")), "").unwrap();
+        }
+        std::fs::write(cfg.directory.join("notes.txt"), "").unwrap();
+
+        // `VACUUM INTO` inherits the open flags, so an in-memory source writes nothing
+        let source = dir.join("source.db");
+        std::fs::write(&source, "").unwrap();
+        let db = BotDatabase::new(&source, &DatabaseCfg::default())
+            .await
+            .unwrap();
+        db.migrate().await.unwrap();
+        db.timezone().set_user(UserId::new(1), Tokyo).await.unwrap();
+        let backup = db.backup(&cfg).await.unwrap();
+        assert!(backup.size > 0);
+        let kept = list(&cfg.directory).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1], backup.path);
+        assert!(cfg.directory.join("notes.txt").exists());
+
+        // the live database as a crashed bot leaves it, with a commit only in the WAL
+        db.timezone().set_user(UserId::new(2), Seoul).await.unwrap();
+        let live = dir.join("live.db");
+        std::fs::copy(&source, &live).unwrap();
+        std::fs::copy(sibling(&source, "-wal"), sibling(&live, "-wal")).unwrap();
+        let running = RunLock::acquire(&live).unwrap();
+        assert!(restore(&live, &backup.path).await.is_err());
+        drop(running);
+        let previous = restore(&live, &backup.path).await.unwrap();
+        assert!(!sibling(&live, "-wal").exists());
+        let before = BotDatabase::new(&previous, &DatabaseCfg::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            before.timezone().get_user(UserId::new(2)).await.unwrap(),
+            Some(Seoul)
+        );
+        let restored = BotDatabase::new(&live, &DatabaseCfg::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            restored.timezone().get_user(UserId::new(1)).await.unwrap(),
+            Some(Tokyo)
+        );
+        assert_eq!(
+            restored.timezone().get_user(UserId::new(2)).await.unwrap(),
+            None
+        );
+
+        // a corrupt backup is rejected before anything is touched
+        let corrupt = cfg.directory.join(format!("{PREFIX}corrupt.db"));
+        std::fs::write(&corrupt, "not a database").unwrap();
+        assert!(restore(&live, &corrupt).await.is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,330 @@
+use chrono::DateTime;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{
+    ConnectionTrait, DatabaseTransaction, DbBackend, FromQueryResult, JsonValue, Statement,
+    TransactionTrait, Value,
+};
+use snafu::{OptionExt, ResultExt, whatever};
+
+use super::BotDatabase;
+use crate::error::BotError;
+
+/// Bookkeeping table of sea-orm migrations, never exported
+const MIGRATIONS_TABLE: &str = "seaql_migrations";
+
+/// Row count of a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: i64,
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn to_value(json: &JsonValue, timestamp: bool) -> Result<Value, BotError> {
+    Ok(match json {
+        // bound as a timestamp so it's stored in the same text format sea-orm writes and
+        // compares with, Postgres exports `…Z` where SQLite has `…+00:00`
+        JsonValue::String(s) if timestamp => match DateTime::parse_from_rfc3339(s) {
+            Ok(t) => Value::ChronoDateTimeWithTimeZone(Some(Box::new(t))),
+            Err(_) => whatever!("Invalid timestamp in export: {s}"),
+        },
+        JsonValue::Null => Value::String(None),
+        JsonValue::Bool(b) => Value::Bool(Some(*b)),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::BigInt(Some(i)),
+            None => Value::Double(n.as_f64()),
+        },
+        JsonValue::String(s) => Value::String(Some(Box::new(s.to_owned()))),
+        other => whatever!("Unsupported value in export: {other}"),
+    })
+}
+
+/// Insert one exported row
+///
+/// Postgres won't coerce text parameters into typed columns, so the row is handed over as JSON
+/// and converted with the table's own row type.
+async fn insert(
+    txn: &DatabaseTransaction,
+    name: &str,
+    row: &serde_json::Map<String, serde_json::Value>,
+    timestamps: &[String],
+) -> Result<(), BotError> {
+    let backend = txn.get_database_backend();
+    let stmt = match backend {
+        DbBackend::Postgres => Statement::from_sql_and_values(
+            backend,
+            format!(
+                "INSERT INTO {0} SELECT * FROM json_populate_record(NULL::{0}, $1::json)",
+                quote(name)
+            ),
+            [Value::String(Some(Box::new(
+                serde_json::to_string(row).whatever_context::<&str, BotError>("Invalid row")?,
+            )))],
+        ),
+        _ => {
+            let columns = row.keys().map(|c| quote(c)).collect::<Vec<_>>();
+            let values = row
+                .iter()
+                .map(|(c, v)| to_value(v, timestamps.contains(c)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                quote(name),
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            Statement::from_sql_and_values(backend, sql, values)
+        }
+    };
+    txn.execute(stmt).await?;
+    Ok(())
+}
+
+/// Timestamp columns of a SQLite table, Postgres parses them from the JSON row itself
+async fn timestamps(txn: &DatabaseTransaction, name: &str) -> Result<Vec<String>, BotError> {
+    let backend = txn.get_database_backend();
+    if backend != DbBackend::Sqlite {
+        return Ok(Vec::new());
+    }
+    let rows = txn
+        .query_all(Statement::from_sql_and_values(
+            backend,
+            "SELECT name FROM pragma_table_info(?) WHERE type LIKE 'timestamp%'",
+            [name.into()],
+        ))
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| row.try_get::<String>("", "name"))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Move Postgres sequences past the imported ids, SQLite derives them from the rows
+async fn reset_sequences(txn: &DatabaseTransaction, name: &str) -> Result<(), BotError> {
+    let backend = txn.get_database_backend();
+    if backend != DbBackend::Postgres {
+        return Ok(());
+    }
+    let serial = txn
+        .query_all(Statement::from_sql_and_values(
+            backend,
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = \
+             current_schema() AND table_name = $1 AND column_default LIKE 'nextval%'",
+            [name.into()],
+        ))
+        .await?;
+    for row in serial {
+        let column: String = row.try_get("", "column_name")?;
+        txn.execute(Statement::from_sql_and_values(
+            backend,
+            format!(
+                "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({}), 0) + 1, false) \
+                 FROM {}",
+                quote(&column),
+                quote(name)
+            ),
+            [quote(name).into(), column.into()],
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+impl BotDatabase {
+    /// Apply all pending migrations
+    pub async fn migrate(&self) -> Result<(), BotError> {
+        Ok(Migrator::up(&self.db, None).await?)
+    }
+
+    /// Names of the application's tables
+    pub async fn tables(&self) -> Result<Vec<String>, BotError> {
+        let backend = self.db.get_database_backend();
+        let sql = match backend {
+            DbBackend::Postgres => {
+                "SELECT table_name::text AS name FROM information_schema.tables WHERE \
+                 table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY name"
+            }
+            _ => {
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+                 ORDER BY name"
+            }
+        };
+        let stmt = Statement::from_string(backend, sql);
+        let rows = self.db.query_all(stmt).await?;
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get::<String>("", "name"))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|name| name != MIGRATIONS_TABLE)
+            .collect())
+    }
+
+    /// Row counts of every table
+    pub async fn table_stats(&self) -> Result<Vec<TableStats>, BotError> {
+        let mut stats = Vec::new();
+        for name in self.tables().await? {
+            let stmt = Statement::from_string(
+                self.db.get_database_backend(),
+                format!("SELECT COUNT(*) AS count FROM {}", quote(&name)),
+            );
+            let rows = match self.db.query_one(stmt).await? {
+                Some(row) => row.try_get("", "count")?,
+                None => 0,
+            };
+            stats.push(TableStats { name, rows });
+        }
+        Ok(stats)
+    }
+
+    /// Every row of every table as `{ table: [row, ...] }`
+    pub async fn export(&self) -> Result<JsonValue, BotError> {
+        let mut tables = Vec::new();
+        for name in self.tables().await? {
+            let stmt = Statement::from_string(
+                self.db.get_database_backend(),
+                format!("SELECT * FROM {}", quote(&name)),
+            );
+            let rows = JsonValue::find_by_statement(stmt).all(&self.db).await?;
+            tables.push((name, JsonValue::Array(rows)));
+        }
+        Ok(JsonValue::Object(tables.into_iter().collect()))
+    }
+
+    /// Insert rows produced by [`BotDatabase::export`] in a single transaction
+    ///
+    /// Tables are emptied first when `replace` is set, otherwise conflicting rows fail the import.
+    pub async fn import(&self, data: &JsonValue, replace: bool) -> Result<u64, BotError> {
+        let tables = data
+            .as_object()
+            .whatever_context::<&str, BotError>("Export must be an object of tables")?;
+        let known = self.tables().await?;
+        let backend = self.db.get_database_backend();
+        let txn = self.db.begin().await?;
+        let mut imported = 0;
+        for (name, rows) in tables {
+            if !known.contains(name) {
+                whatever!("Unknown table `{name}`, run `migrate` first?");
+            }
+            if replace {
+                txn.execute(Statement::from_string(
+                    backend,
+                    format!("DELETE FROM {}", quote(name)),
+                ))
+                .await?;
+            }
+            let rows = rows
+                .as_array()
+                .with_whatever_context::<_, String, BotError>(|| {
+                    format!("Table `{name}` must be an array of rows")
+                })?;
+            let timestamps = timestamps(&txn, name).await?;
+            for row in rows {
+                let row = row
+                    .as_object()
+                    .with_whatever_context::<_, String, BotError>(|| {
+                        format!("Row in `{name}` must be an object")
+                    })?;
+                insert(&txn, name, row, &timestamps).await?;
+                imported += 1;
+            }
+            reset_sequences(&txn, name).await?;
+        }
+        txn.commit().await?;
+        // exports from before the rollups existed only carry raw messages
+        if tables.contains_key("messages") && !tables.contains_key("daily_activity") {
+            self.message().rebuild_activity().await?;
+        }
+        Ok(imported)
+    }
+
+    /// Rebuild the database file to reclaim free pages
+    pub async fn vacuum(&self) -> Result<(), BotError> {
+        self.db.execute_unprepared("VACUUM").await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serenity::all::{ChannelId, UserId};
+
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        for_each_backend(async |db| {
+            db.usage()
+                .record(
+                    "ping",
+                    None,
+                    ChannelId::new(1),
+                    UserId::new(2),
+                    std::time::Duration::from_millis(12),
+                    Some("io"),
+                )
+                .await
+                .unwrap();
+            db.timezone()
+                .set_user(UserId::new(2), chrono_tz::Asia::Tokyo)
+                .await
+                .unwrap();
+
+            let export = db.export().await.unwrap();
+            assert_eq!(export["command_usage"].as_array().unwrap().len(), 1);
+            assert!(export.get(MIGRATIONS_TABLE).is_none());
+
+            // into a fresh database, which is SQLite for both backends, so this doubles as the
+            // Postgres to SQLite migration
+            let fresh = BotDatabase::new_memory().await.unwrap();
+            fresh.migrate().await.unwrap();
+            assert_eq!(fresh.import(&export, false).await.unwrap(), 2);
+            assert_eq!(
+                fresh.timezone().get_user(UserId::new(2)).await.unwrap(),
+                Some(chrono_tz::Asia::Tokyo)
+            );
+            // timestamps must compare like natively written ones, Postgres exports `…Z`
+            let at = export["command_usage"][0]["invoked_at"].as_str().unwrap();
+            let at = DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+            let usage = fresh.usage().stats(Some(at)).await.unwrap();
+            assert_eq!((usage[0].calls, usage[0].errors), (1, 1));
+            let later = at + chrono::Duration::nanoseconds(1);
+            assert!(fresh.usage().stats(Some(later)).await.unwrap().is_empty());
+            if db.backend() == DbBackend::Sqlite {
+                assert_eq!(fresh.export().await.unwrap(), export);
+            }
+
+            // importing again only works when replacing
+            assert!(db.import(&export, false).await.is_err());
+            assert_eq!(db.import(&export, true).await.unwrap(), 2);
+            assert_eq!(db.export().await.unwrap(), export);
+
+            let stats = db.table_stats().await.unwrap();
+            assert!(stats.contains(&TableStats {
+                name: "timezones".to_string(),
+                rows: 1
+            }));
+            // sequences continue after the imported ids
+            db.usage()
+                .record(
+                    "ping",
+                    None,
+                    ChannelId::new(1),
+                    UserId::new(2),
+                    std::time::Duration::ZERO,
+                    None,
+                )
+                .await
+                .unwrap();
+            let usage = db.usage().stats(None).await.unwrap();
+            assert_eq!((usage[0].calls, usage[0].errors), (2, 1));
+            db.vacuum().await.unwrap();
+            assert!(db.size().await.unwrap() > 0);
+        })
+        .await;
+    }
+}
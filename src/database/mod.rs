@@ -0,0 +1,224 @@
+use std::{
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use sea_orm::{
+    ConnectionTrait, Database, DatabaseConnection, DbBackend, SqlxSqliteConnector, Statement,
+    sqlx::{
+        ConnectOptions,
+        sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    },
+};
+use serenity::{all::Context, prelude::TypeMapKey};
+use snafu::{OptionExt, ResultExt};
+
+pub use self::{backup::Backup, maintenance::TableStats};
+use crate::{config::DatabaseCfg, error::BotError};
+
+pub mod backup;
+mod maintenance;
+
+#[derive(Debug, Clone)]
+pub struct BotDatabase {
+    db: DatabaseConnection,
+}
+
+impl TypeMapKey for BotDatabase {
+    type Value = BotDatabase;
+}
+
+#[allow(dead_code)]
+pub(crate) trait GetDb {
+    async fn db(&self) -> Result<BotDatabase, BotError>;
+}
+
+impl GetDb for Context {
+    async fn db(&self) -> Result<BotDatabase, BotError> {
+        self.data
+            .read()
+            .await
+            .get::<BotDatabase>()
+            .cloned()
+            .whatever_context::<&str, BotError>("Failed to get BotDatabase from context")
+    }
+}
+
+impl BotDatabase {
+    /// Open the SQLite database at `path`, creating it if missing
+    pub async fn new(path: impl AsRef<Path>, cfg: &DatabaseCfg) -> Result<Self, BotError> {
+        Self::sqlite(SqliteConnectOptions::new().filename(path), cfg).await
+    }
+
+    /// Open a `sqlite://` or, with the `postgres` feature, a `postgres://` URL
+    pub async fn connect(url: &str, cfg: &DatabaseCfg) -> Result<Self, BotError> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            #[cfg(feature = "postgres")]
+            return Self::postgres(url, cfg).await;
+            #[cfg(not(feature = "postgres"))]
+            snafu::whatever!("Postgres requires building with `--features postgres`");
+        }
+        let options = SqliteConnectOptions::from_str(url)
+            .whatever_context::<&str, BotError>("Invalid SQLite database URL")?;
+        Self::sqlite(options, cfg).await
+    }
+
+    /// WAL lets readers proceed during writes and `synchronous=NORMAL` is durable in WAL mode
+    /// except across power loss, which is fine for stats.
+    async fn sqlite(options: SqliteConnectOptions, cfg: &DatabaseCfg) -> Result<Self, BotError> {
+        let options = options
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_millis(cfg.busy_timeout_ms))
+            .log_statements(cfg.log_level.into());
+        let pool = SqlitePoolOptions::new()
+            .max_connections(cfg.max_connections)
+            .acquire_timeout(Duration::from_secs(cfg.acquire_timeout_secs))
+            .connect_with(options)
+            .await
+            .whatever_context::<&str, BotError>("Failed to open database")?;
+        Ok(BotDatabase {
+            db: SqlxSqliteConnector::from_sqlx_sqlite_pool(pool),
+        })
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn postgres(url: &str, cfg: &DatabaseCfg) -> Result<Self, BotError> {
+        use sea_orm::{
+            SqlxPostgresConnector,
+            sqlx::postgres::{PgConnectOptions, PgPoolOptions},
+        };
+        let options = PgConnectOptions::from_str(url)
+            .whatever_context::<&str, BotError>("Invalid Postgres database URL")?
+            .log_statements(cfg.log_level.into());
+        let pool = PgPoolOptions::new()
+            .max_connections(cfg.max_connections)
+            .acquire_timeout(Duration::from_secs(cfg.acquire_timeout_secs))
+            .connect_with(options)
+            .await
+            .whatever_context::<&str, BotError>("Failed to connect to Postgres")?;
+        Ok(BotDatabase {
+            db: SqlxPostgresConnector::from_sqlx_postgres_pool(pool),
+        })
+    }
+
+    pub fn backend(&self) -> DbBackend {
+        self.db.get_database_backend()
+    }
+
+    /// Open an existing database file without changing it
+    pub async fn open_read_only(path: impl AsRef<Path>) -> Result<Self, BotError> {
+        let options = SqliteConnectOptions::new().filename(path).read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .whatever_context::<&str, BotError>("Failed to open database")?;
+        Ok(BotDatabase {
+            db: SqlxSqliteConnector::from_sqlx_sqlite_pool(pool),
+        })
+    }
+
+    pub async fn new_memory() -> Result<Self, BotError> {
+        let db = Database::connect("sqlite::memory:").await?;
+        Ok(BotDatabase { db })
+    }
+
+    pub fn inner(&self) -> &DatabaseConnection {
+        &self.db
+    }
+
+    /// Measure the round trip of a trivial query
+    pub async fn latency(&self) -> Result<Duration, BotError> {
+        let start = Instant::now();
+        self.db.ping().await?;
+        Ok(start.elapsed())
+    }
+
+    /// Bytes used on disk
+    pub async fn size(&self) -> Result<i64, BotError> {
+        let sql = match self.backend() {
+            DbBackend::Postgres => "SELECT pg_database_size(current_database())::bigint AS size",
+            _ => {
+                "SELECT page_count * page_size as size FROM pragma_page_count(), pragma_page_size()"
+            }
+        };
+        let stmt = Statement::from_string(self.backend(), sql);
+
+        let result = self.db.query_one(stmt).await?;
+        if let Some(row) = result {
+            let size: i64 = row.try_get("", "size")?;
+            Ok(size)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Points the tests at a Postgres server, e.g. `postgres://postgres@localhost/postgres`
+#[cfg(all(test, feature = "postgres"))]
+const TEST_POSTGRES_URL: &str = "DOG_BOT_TEST_POSTGRES_URL";
+
+/// A migrated Postgres database isolated in a fresh schema, `None` when unavailable
+#[cfg(all(test, feature = "postgres"))]
+async fn test_postgres() -> Option<(BotDatabase, String)> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use sea_orm::{
+        SqlxPostgresConnector,
+        sqlx::postgres::{PgConnectOptions, PgPoolOptions},
+    };
+
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let url = std::env::var(TEST_POSTGRES_URL).ok()?;
+    let schema = format!(
+        "test_{}_{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    );
+    let admin = match BotDatabase::connect(&url, &DatabaseCfg::default()).await {
+        Ok(admin) => admin,
+        Err(e) => {
+            eprintln!("Skipping Postgres: {e}");
+            return None;
+        }
+    };
+    admin
+        .db
+        .execute_unprepared(&format!(
+            "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}"
+        ))
+        .await
+        .unwrap();
+    admin.db.close().await.unwrap();
+
+    let options = PgConnectOptions::from_str(&url)
+        .unwrap()
+        .options([("search_path", schema.as_str())]);
+    let pool = PgPoolOptions::new().connect_with(options).await.unwrap();
+    let db = BotDatabase {
+        db: SqlxPostgresConnector::from_sqlx_postgres_pool(pool),
+    };
+    db.migrate().await.unwrap();
+    Some((db, schema))
+}
+
+/// Run `test` against a migrated in-memory SQLite database and, when `DOG_BOT_TEST_POSTGRES_URL`
+/// is set, a Postgres one
+#[cfg(test)]
+pub(crate) async fn for_each_backend(test: impl AsyncFn(&BotDatabase)) {
+    let sqlite = BotDatabase::new_memory().await.unwrap();
+    sqlite.migrate().await.unwrap();
+    test(&sqlite).await;
+
+    #[cfg(feature = "postgres")]
+    if let Some((db, schema)) = test_postgres().await {
+        test(&db).await;
+        db.db
+            .execute_unprepared(&format!("DROP SCHEMA {schema} CASCADE"))
+            .await
+            .ok();
+    }
+}
@@ -36,3 +36,16 @@ pub enum BotError {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 }
+
+impl BotError {
+    /// A short, stable name for the error variant, suitable as a metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotError::JemallocCtlError { .. } => "jemalloc",
+            BotError::SeaOrmError { .. } => "database",
+            BotError::IoError { .. } => "io",
+            BotError::SerenityError { .. } => "serenity",
+            BotError::GenericError { .. } => "generic",
+        }
+    }
+}
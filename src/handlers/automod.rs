@@ -0,0 +1,169 @@
+use std::time::Instant;
+
+use chrono::Utc;
+use serenity::{
+    all::{
+        Colour, CreateEmbed, CreateEmbedAuthor, CreateMessage, EditMember, GuildId, Mentionable,
+    },
+    async_trait,
+    model::channel::Message,
+    prelude::*,
+};
+use snafu::OptionExt;
+use tracing::{info, warn};
+
+use crate::{
+    config::{BotCfg, GetCfg},
+    database::{BotDatabase, GetDb},
+    error::BotError,
+    moderation::{AutoMod, Rule, RuleAction, Sample, Trigger, first_match, notify, open_case},
+    repo::CaseAction,
+};
+
+/// Applies each guild's automod rules to incoming messages
+pub struct AutoModHandler;
+
+/// Admins and the guild owner are never moderated automatically
+fn exempt(ctx: &Context, cfg: &BotCfg, guild_id: GuildId, msg: &Message) -> bool {
+    cfg.extra_admin_user_ids.contains(&msg.author.id)
+        || msg
+            .member
+            .as_ref()
+            .is_some_and(|m| m.roles.iter().any(|id| cfg.admin_role_ids.contains(id)))
+        || ctx
+            .cache
+            .guild(guild_id)
+            .is_some_and(|g| g.owner_id == msg.author.id)
+}
+
+/// Carry out the action of `rule`, which `msg` triggered
+async fn enforce(
+    ctx: &Context,
+    cfg: &BotCfg,
+    db: &BotDatabase,
+    guild_id: GuildId,
+    msg: &Message,
+    rule: &Rule,
+) -> Result<(), BotError> {
+    info!(
+        %guild_id,
+        user_id = %msg.author.id,
+        rule = rule.id,
+        trigger = rule.trigger.label(),
+        "Automod rule triggered"
+    );
+    if let RuleAction::Flush(toilet) = rule.action {
+        let embed = CreateEmbed::new()
+            .author(CreateEmbedAuthor::new(&msg.author.name).icon_url(msg.author.face()))
+            .description(&msg.content)
+            .color(Colour::DARK_GREY)
+            .field("来源", msg.channel_id.mention().to_string(), true)
+            .field(
+                "规则",
+                format!("#{} {}", rule.id, rule.trigger.label()),
+                true,
+            )
+            .timestamp(msg.timestamp);
+        // the message goes either way, a broken toilet channel must not leave it up
+        if let Err(e) = toilet
+            .send_message(ctx, CreateMessage::new().embed(embed))
+            .await
+        {
+            warn!(
+                %guild_id,
+                %toilet,
+                rule = rule.id,
+                "Failed to repost to the toilet channel: {}",
+                e
+            );
+        }
+    }
+    msg.delete(ctx).await?;
+
+    let (action, duration) = match rule.action {
+        RuleAction::Warn => (CaseAction::Warn, None),
+        RuleAction::Timeout(duration) => {
+            let until = Utc::now() + duration;
+            guild_id
+                .edit_member(
+                    ctx,
+                    msg.author.id,
+                    EditMember::new()
+                        .disable_communication_until_datetime(until.into())
+                        .audit_log_reason(&format!("Automod rule #{}", rule.id)),
+                )
+                .await?;
+            (CaseAction::Timeout, Some(duration))
+        }
+        RuleAction::Delete | RuleAction::Flush(_) => return Ok(()),
+    };
+    let reason = format!("自动管理: 规则 #{} ({})", rule.id, rule.trigger.label());
+    let bot_id = ctx.cache.current_user().id;
+    if cfg.moderation.dm_targets {
+        let guild = ctx
+            .cache
+            .guild(guild_id)
+            .map(|g| g.name.clone())
+            .unwrap_or_else(|| "服务器".to_string());
+        notify(ctx, msg.author.id, &guild, action, Some(&reason), duration).await;
+    }
+    open_case(
+        ctx,
+        cfg,
+        db,
+        guild_id,
+        action,
+        msg.author.id,
+        bot_id,
+        Some(&reason),
+        duration,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn check(ctx: &Context, msg: &Message) -> Result<(), BotError> {
+    let Some(guild_id) = msg.guild_id else {
+        return Ok(());
+    };
+    if msg.author.bot {
+        return Ok(());
+    }
+    let cfg = ctx.cfg().await?.load_full();
+    if exempt(ctx, &cfg, guild_id, msg) {
+        return Ok(());
+    }
+    let automod = ctx
+        .data
+        .read()
+        .await
+        .get::<AutoMod>()
+        .cloned()
+        .whatever_context::<&str, BotError>("Failed to get AutoMod from context")?;
+    let db = ctx.db().await?;
+    let rules = automod.rules(&db, guild_id).await?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    // History is only kept for guilds that look for duplicates
+    let repeats = match rules
+        .iter()
+        .any(|r| matches!(r.trigger, Trigger::Duplicates { .. }))
+    {
+        true => automod.remember(guild_id, msg.author.id, &msg.content, Instant::now()),
+        false => Vec::new(),
+    };
+    match first_match(&rules, &Sample::new(msg, &repeats)) {
+        Some(rule) => enforce(ctx, &cfg, &db, guild_id, msg, rule).await,
+        None => Ok(()),
+    }
+}
+
+#[async_trait]
+impl EventHandler for AutoModHandler {
+    async fn message(&self, ctx: Context, msg: Message) {
+        if let Err(e) = check(&ctx, &msg).await {
+            warn!(message_id = %msg.id, "Failed to apply automod rules: {}", e);
+        }
+    }
+}
@@ -1,3 +1,9 @@
+mod automod;
 mod ping;
+mod raid;
+mod tracker;
 
+pub use automod::AutoModHandler;
 pub use ping::PingHandler;
+pub use raid::RaidHandler;
+pub use tracker::MessageTracker;
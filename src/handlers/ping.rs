@@ -1,5 +1,4 @@
 use chrono::Utc;
-use owo_colors::OwoColorize as _;
 use serenity::{
     all::{EditMessage, GuildId, Ready},
     async_trait,
@@ -8,7 +7,7 @@ use serenity::{
 };
 use tracing::{info, warn};
 
-use crate::error::BotError;
+use crate::{error::BotError, server::GatewayReady};
 
 pub struct PingHandler;
 
@@ -17,17 +16,14 @@ impl EventHandler for PingHandler {
     async fn cache_ready(&self, ctx: Context, guilds: Vec<GuildId>) {
         // This is called when the cache is ready.
         // list all guilds the bot is in
-        info!(
-            "Cache is ready! Bot is in {} guilds.",
-            guilds.len().to_string().green()
-        );
+        info!(guilds = guilds.len(), "Cache is ready");
         for guild in guilds {
             let guild_name = ctx
                 .cache
                 .guild(guild)
                 .map(|g| g.name.to_owned())
                 .unwrap_or("Uncached Guild".to_string());
-            info!("Connected to: {} ({})", guild_name.green(), guild);
+            info!(guild_id = %guild, guild = %guild_name, "Connected to guild");
         }
     }
 
@@ -73,9 +69,12 @@ impl EventHandler for PingHandler {
         }
     }
 
-    async fn ready(&self, _ctx: Context, ready: Ready) {
+    async fn ready(&self, ctx: Context, ready: Ready) {
         // This is called when the bot is ready and has connected to Discord.
         // You can use this to set the bot's activity or status.
-        info!("{} is connected!", ready.user.name.green());
+        info!(user = %ready.user.name, "Connected to gateway");
+        if let Some(flag) = ctx.data.read().await.get::<GatewayReady>() {
+            flag.set();
+        }
     }
 }
@@ -0,0 +1,114 @@
+use std::time::Instant;
+
+use serenity::{
+    all::{CreateEmbed, CreateEmbedFooter, Member, Mentionable, Timestamp, colours::branding::RED},
+    async_trait,
+    prelude::*,
+};
+use snafu::OptionExt;
+use tracing::warn;
+
+use crate::{
+    config::GetCfg,
+    database::GetDb,
+    error::BotError,
+    moderation::{
+        RaidGuard, SurgeKind, post_log,
+        raid::{describe, is_new_account, lock},
+    },
+};
+
+/// Mentions listed in a raid alert before the rest are summarized
+const SHOWN: usize = 30;
+
+/// Watches member joins for raids in guilds with anti-raid thresholds
+pub struct RaidHandler;
+
+async fn check(ctx: &Context, member: &Member) -> Result<(), BotError> {
+    let guild_id = member.guild_id;
+    let cfg = ctx.cfg().await?.load_full();
+    let Some(raid) = cfg.moderation.raid.get(&guild_id).copied() else {
+        return Ok(());
+    };
+    let guard = ctx
+        .data
+        .read()
+        .await
+        .get::<RaidGuard>()
+        .cloned()
+        .whatever_context::<&str, BotError>("Failed to get RaidGuard from context")?;
+    let new_account = is_new_account(member.user.id, Timestamp::now(), raid.account_age_days);
+    let Some(surge) = guard.record(guild_id, &raid, Instant::now(), member.user.id, new_account)
+    else {
+        return Ok(());
+    };
+    let db = ctx.db().await?;
+    // joins keep coming in while locked down, that's what the lockdown is for
+    if db.lockdown().get(guild_id).await?.is_some() {
+        return Ok(());
+    }
+    warn!(
+        %guild_id,
+        joins = surge.members.len(),
+        new_accounts = surge.new_accounts,
+        "Raid detected"
+    );
+
+    let description = match surge.kind {
+        SurgeKind::Joins => format!(
+            "{}s 内有 {} 名成员加入，其中 {} 个新账号",
+            raid.window_secs,
+            surge.members.len(),
+            surge.new_accounts
+        ),
+        SurgeKind::NewAccounts => format!(
+            "{}s 内有 {} 个注册不足 {} 天的账号加入",
+            raid.window_secs, surge.new_accounts, raid.account_age_days
+        ),
+    };
+    let mut members = surge
+        .members
+        .iter()
+        .take(SHOWN)
+        .map(|id| id.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if surge.members.len() > SHOWN {
+        members.push_str(&format!(" … 另有 {} 人", surge.members.len() - SHOWN));
+    }
+    let mut embed = CreateEmbed::new()
+        .title("🚨 检测到突袭")
+        .description(description)
+        .color(RED)
+        .field("最近加入", members, false)
+        .timestamp(Timestamp::now());
+
+    let guild = ctx.cache.guild(guild_id).map(|g| g.clone());
+    embed = match (raid.lockdown, guild) {
+        (false, _) => embed.field("锁定", "未开启自动锁定，可使用 `/lockdown on`", false),
+        (true, None) => embed.field("锁定", "⚠️ 服务器未缓存，无法自动锁定", false),
+        (true, Some(guild)) => match lock(ctx, &db, &guild, &raid, None).await? {
+            Some((lockdown, failed)) => {
+                let mut lines = describe(&lockdown, raid.slowmode_secs);
+                if failed > 0 {
+                    lines.push(format!("⚠️ {failed} 项更改失败，见日志"));
+                }
+                embed
+                    .field("已自动锁定", lines.join("\n"), false)
+                    .footer(CreateEmbedFooter::new("使用 /lockdown off 解除锁定"))
+            }
+            None => embed,
+        },
+    };
+    post_log(ctx, &cfg, guild_id, embed).await;
+    Ok(())
+}
+
+#[async_trait]
+impl EventHandler for RaidHandler {
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        if let Err(e) = check(&ctx, &new_member).await {
+            warn!(guild_id = %new_member.guild_id, "Failed to track member join: {}", e);
+        }
+    }
+}
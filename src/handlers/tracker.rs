@@ -0,0 +1,218 @@
+use chrono::Utc;
+use entities::messages::Model as MessageRecord;
+use serenity::{
+    all::{ChannelId, GuildId, MessageId, MessageUpdateEvent, Reaction, ReactionType},
+    async_trait,
+    model::channel::Message,
+    prelude::*,
+};
+use tracing::warn;
+
+use crate::{
+    config::{GetCfg, TrackingCfg},
+    database::GetDb,
+    error::BotError,
+};
+
+/// Records guild messages for the statistics commands when `tracking.messages` is on
+///
+/// Deletions are always applied so turning tracking off never leaves stale records behind.
+pub struct MessageTracker;
+
+async fn tracking(ctx: &Context) -> TrackingCfg {
+    match ctx.cfg().await {
+        Ok(cfg) => cfg.load().tracking,
+        Err(_) => TrackingCfg::default(),
+    }
+}
+
+/// Parent of a thread or forum post, `None` for regular channels
+fn thread_parent(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> Option<ChannelId> {
+    ctx.cache
+        .guild(guild_id)?
+        .threads
+        .iter()
+        .find(|t| t.id == channel_id)
+        .and_then(|t| t.parent_id)
+}
+
+/// Stable key of an emoji, custom emojis are renamed without changing their id
+fn emoji_key(emoji: &ReactionType) -> String {
+    match emoji {
+        ReactionType::Custom { id, .. } => id.to_string(),
+        ReactionType::Unicode(s) => s.to_owned(),
+        other => other.to_string(),
+    }
+}
+
+async fn forget(ctx: &Context, message_ids: &[MessageId]) {
+    let result = match ctx.db().await {
+        Ok(db) => db.message().delete(message_ids).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        warn!(
+            count = message_ids.len(),
+            "Failed to forget deleted messages: {}", e
+        );
+    }
+}
+
+async fn add_reaction(ctx: &Context, reaction: &Reaction) -> Result<(), BotError> {
+    let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+        return Ok(());
+    };
+    if reaction.member.as_ref().is_some_and(|m| m.user.bot) {
+        return Ok(());
+    }
+    ctx.db()
+        .await?
+        .reaction()
+        .add(
+            reaction.message_id,
+            user_id,
+            &emoji_key(&reaction.emoji),
+            reaction.message_author_id,
+            guild_id,
+            reaction.channel_id,
+            Utc::now(),
+        )
+        .await
+}
+
+#[async_trait]
+impl EventHandler for MessageTracker {
+    async fn message(&self, ctx: Context, msg: Message) {
+        let Some(guild_id) = msg.guild_id else {
+            return;
+        };
+        if msg.author.bot {
+            return;
+        }
+        let tracking = tracking(&ctx).await;
+        if !tracking.messages {
+            return;
+        }
+        let record = MessageRecord {
+            message_id: msg.id.get() as i64,
+            user_id: msg.author.id.get() as i64,
+            guild_id: guild_id.get() as i64,
+            channel_id: msg.channel_id.get() as i64,
+            timestamp: msg.timestamp.to_utc().into(),
+            edit_count: 0,
+            edited_at: None,
+            attachments: match tracking.attachments {
+                true => msg.attachments.len() as i64,
+                false => 0,
+            },
+            parent_id: tracking
+                .threads
+                .then(|| thread_parent(&ctx, guild_id, msg.channel_id))
+                .flatten()
+                .map(|id| id.get() as i64),
+        };
+        let result = match ctx.db().await {
+            Ok(db) => db.message().record_many(&[record]).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(message_id = %msg.id, "Failed to record message: {}", e);
+        }
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _: Option<Message>,
+        _: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // embeds resolving also update a message, only edits carry a new timestamp
+        let Some(edited_at) = event.edited_timestamp else {
+            return;
+        };
+        let tracking = tracking(&ctx).await;
+        if event.guild_id.is_none() || !tracking.messages || !tracking.edits {
+            return;
+        }
+        let result = match ctx.db().await {
+            Ok(db) => db.message().record_edit(event.id, edited_at).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(message_id = %event.id, "Failed to record edit: {}", e);
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _: ChannelId,
+        message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if guild_id.is_some() {
+            forget(&ctx, &[message_id]).await;
+        }
+    }
+
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        _: ChannelId,
+        message_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if guild_id.is_some() {
+            forget(&ctx, &message_ids).await;
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let tracking = tracking(&ctx).await;
+        if !tracking.messages || !tracking.reactions {
+            return;
+        }
+        if let Err(e) = add_reaction(&ctx, &reaction).await {
+            warn!(message_id = %reaction.message_id, "Failed to record reaction: {}", e);
+        }
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        let result = match ctx.db().await {
+            Ok(db) => {
+                db.reaction()
+                    .remove(reaction.message_id, user_id, &emoji_key(&reaction.emoji))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(message_id = %reaction.message_id, "Failed to forget reaction: {}", e);
+        }
+    }
+
+    async fn reaction_remove_all(&self, ctx: Context, _: ChannelId, message_id: MessageId) {
+        let result = match ctx.db().await {
+            Ok(db) => db.reaction().clear(message_id, None).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(%message_id, "Failed to forget reactions: {}", e);
+        }
+    }
+
+    async fn reaction_remove_emoji(&self, ctx: Context, reaction: Reaction) {
+        let emoji = emoji_key(&reaction.emoji);
+        let result = match ctx.db().await {
+            Ok(db) => db.reaction().clear(reaction.message_id, Some(&emoji)).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!(message_id = %reaction.message_id, "Failed to forget reactions: {}", e);
+        }
+    }
+}
@@ -1,21 +1,78 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
+pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod database;
 pub mod error;
 pub mod handlers;
+pub mod logging;
+pub mod metrics;
+pub mod moderation;
 mod repo;
+pub mod sampler;
+pub mod server;
 pub mod utils;
 
 #[derive(Parser)]
 pub struct Args {
-    #[clap(short, long, default_value = "config.json")]
+    #[clap(short, long, default_value = "config.json", global = true)]
     /// Path to the configuration file
     pub config: PathBuf,
     /// Path to the database file
-    #[clap(short, long, default_value = "sqlite.db")]
+    #[clap(short, long, default_value = "sqlite.db", global = true)]
     pub db: PathBuf,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Connect to Discord and run the bot (default)
+    Run,
+    /// Apply pending database migrations
+    Migrate,
+    /// Validate the configuration file and exit
+    CheckConfig,
+    /// Print the JSON Schema of the configuration file
+    PrintSchema,
+    /// Register slash commands over HTTP without connecting to the gateway
+    RegisterCommands {
+        /// Register in this guild only, takes effect immediately
+        #[clap(long)]
+        guild: Option<u64>,
+    },
+    /// Database maintenance
+    #[clap(subcommand)]
+    Db(DbCommand),
+    /// Replace the database with a backup, the bot must be stopped
+    Restore {
+        /// Backup file to restore
+        backup: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Show the file size and row counts
+    Stats,
+    /// Dump every table to a JSON file
+    Export {
+        /// Output file
+        path: PathBuf,
+    },
+    /// Load a JSON file written by `db export`
+    Import {
+        /// Input file
+        path: PathBuf,
+        /// Empty the tables before importing
+        #[clap(long)]
+        replace: bool,
+    },
+    /// Rebuild the database file to reclaim free space
+    Vacuum,
+    /// Write a verified backup into the configured backup directory
+    Backup,
 }
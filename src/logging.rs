@@ -0,0 +1,268 @@
+use std::{
+    io::IsTerminal,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use snafu::ResultExt;
+use tracing::{Subscriber, info, level_filters::LevelFilter, warn};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry,
+    fmt::{MakeWriter, format::Writer, time::FormatTime},
+    layer::{Layered, SubscriberExt},
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt,
+};
+
+use crate::{
+    config::{LogCfg, LogFormat, LogRotation},
+    error::BotError,
+    utils::Zone,
+};
+
+struct TimeFormatter {
+    zone: Zone,
+}
+
+impl FormatTime for TimeFormatter {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        write!(
+            w,
+            "{}",
+            self.zone.format(Utc::now(), "%Y-%m-%d %H:%M:%S%.3f %Z")
+        )
+    }
+}
+
+type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+type FilterHandle = reload::Handle<EnvFilter, Layered<Vec<BoxedLayer<Registry>>, Registry>>;
+
+/// The configured directive, falling back to `RUST_LOG`
+pub fn base_directive(configured: Option<&str>) -> String {
+    configured
+        .map(str::to_owned)
+        .or_else(|| std::env::var(EnvFilter::DEFAULT_ENV).ok())
+        .unwrap_or_default()
+}
+
+/// Parse a filter directive the same way `RUST_LOG` is parsed
+pub(crate) fn parse_filter(directive: &str) -> Result<EnvFilter, BotError> {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::ERROR.into())
+        .parse(directive)
+        .whatever_context::<String, BotError>(format!("Invalid log directive `{directive}`"))
+}
+
+struct LogState {
+    /// Directive from the configuration or `RUST_LOG`
+    base: String,
+    /// Directive currently in effect
+    current: String,
+    /// Pending revert of a temporary override
+    revert: Option<tokio::task::AbortHandle>,
+    /// Bumped on every override or reset so a revert that already woke up and is waiting for
+    /// the lock can tell it was superseded, aborting it is too late by then
+    generation: u64,
+}
+
+/// Runtime control over the global log filter
+#[derive(Clone)]
+pub struct LogControl {
+    handle: FilterHandle,
+    state: Arc<Mutex<LogState>>,
+}
+
+impl std::fmt::Debug for LogControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogControl")
+            .field("current", &self.current())
+            .finish()
+    }
+}
+
+impl LogControl {
+    fn new(handle: FilterHandle, base: String) -> Self {
+        Self {
+            handle,
+            state: Arc::new(Mutex::new(LogState {
+                current: base.to_owned(),
+                base,
+                revert: None,
+                generation: 0,
+            })),
+        }
+    }
+
+    fn apply(&self, state: &mut LogState, directive: &str) -> Result<(), BotError> {
+        self.handle
+            .reload(parse_filter(directive)?)
+            .whatever_context::<&str, BotError>("Failed to reload log filter")?;
+        state.current = directive.to_owned();
+        Ok(())
+    }
+
+    /// The directive currently in effect
+    pub fn current(&self) -> String {
+        self.state
+            .lock()
+            .expect("log state poisoned")
+            .current
+            .to_owned()
+    }
+
+    /// The directive restored once a temporary override expires
+    pub fn base(&self) -> String {
+        self.state
+            .lock()
+            .expect("log state poisoned")
+            .base
+            .to_owned()
+    }
+
+    /// Replace the configured directive, deferring to an active override
+    pub fn set_base(&self, directive: &str) -> Result<(), BotError> {
+        parse_filter(directive)?;
+        let mut state = self.state.lock().expect("log state poisoned");
+        state.base = directive.to_owned();
+        if state.revert.is_none() {
+            self.apply(&mut state, directive)?;
+        }
+        Ok(())
+    }
+
+    /// Apply `directive` now and fall back to the base directive after `timeout`
+    pub fn set_temporary(&self, directive: &str, timeout: Duration) -> Result<(), BotError> {
+        let mut state = self.state.lock().expect("log state poisoned");
+        self.apply(&mut state, directive)?;
+        if let Some(revert) = state.revert.take() {
+            revert.abort();
+        }
+        state.generation += 1;
+        let generation = state.generation;
+        let this = self.to_owned();
+        state.revert = Some(
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                let mut state = this.state.lock().expect("log state poisoned");
+                if state.generation != generation {
+                    return;
+                }
+                state.revert = None;
+                let base = state.base.to_owned();
+                match this.apply(&mut state, &base) {
+                    Ok(()) => info!(directive = %base, "Log filter reverted"),
+                    Err(e) => warn!("Failed to revert log filter: {}", e),
+                }
+            })
+            .abort_handle(),
+        );
+        Ok(())
+    }
+
+    /// Drop any temporary override and restore the base directive
+    pub fn reset(&self) -> Result<(), BotError> {
+        let mut state = self.state.lock().expect("log state poisoned");
+        if let Some(revert) = state.revert.take() {
+            revert.abort();
+        }
+        state.generation += 1;
+        let base = state.base.to_owned();
+        self.apply(&mut state, &base)
+    }
+}
+
+fn fmt_layer<S, W>(format: LogFormat, ansi: bool, zone: Zone, writer: W) -> BoxedLayer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_timer(TimeFormatter { zone })
+        .with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .boxed(),
+    }
+}
+
+/// Install the global subscriber
+///
+/// `directive` takes precedence over `RUST_LOG`. The returned guard flushes the file sink on
+/// drop and must be held until shutdown.
+pub fn init(
+    cfg: &LogCfg,
+    directive: Option<&str>,
+    zone: Zone,
+) -> Result<(LogControl, Option<WorkerGuard>), BotError> {
+    let ansi = std::io::stdout().is_terminal();
+    let mut layers = vec![fmt_layer(cfg.format, ansi, zone, std::io::stdout)];
+
+    let guard = match &cfg.file {
+        Some(file) => {
+            std::fs::create_dir_all(&file.directory)?;
+            let mut builder = RollingFileAppender::builder()
+                .rotation(match file.rotation {
+                    LogRotation::Minutely => Rotation::MINUTELY,
+                    LogRotation::Hourly => Rotation::HOURLY,
+                    LogRotation::Daily => Rotation::DAILY,
+                    LogRotation::Never => Rotation::NEVER,
+                })
+                .filename_prefix(&file.prefix);
+            if let Some(max_files) = file.max_files {
+                builder = builder.max_log_files(max_files);
+            }
+            let appender = builder
+                .build(&file.directory)
+                .whatever_context::<&str, BotError>("Failed to create log file appender")?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(fmt_layer(file.format, false, zone, writer));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    let base = base_directive(directive);
+    let (filter, handle) = reload::Layer::new(parse_filter(&base)?);
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()
+        .whatever_context::<&str, BotError>("Failed to install tracing subscriber")?;
+    Ok((LogControl::new(handle, base), guard))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_temporary_directive_reverts() {
+        let (_layer, handle) = reload::Layer::new(parse_filter("info").unwrap());
+        let log = LogControl::new(handle, "info".to_string());
+        assert!(log.set_temporary("serenity=loud", Duration::ZERO).is_err());
+
+        log.set_temporary("serenity=debug", Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(log.current(), "serenity=debug");
+        // a config reload during an override only changes what is restored
+        log.set_base("warn").unwrap();
+        assert_eq!(log.current(), "serenity=debug");
+
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(log.current(), "warn");
+
+        log.set_temporary("debug", Duration::from_secs(60)).unwrap();
+        log.reset().unwrap();
+        assert_eq!(log.current(), "warn");
+    }
+}
@@ -1,53 +1,106 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use arc_swap::ArcSwap;
-use chrono::{FixedOffset, Utc};
 use clap::Parser;
 use dog_bot_template::{
-    Args, commands::framework, config::BotCfg, database::BotDatabase, error::BotError, handlers::*,
+    Args, Command, cli,
+    commands::framework,
+    config::BotCfg,
+    database::{self, BotDatabase, backup::RunLock},
+    error::BotError,
+    handlers::*,
+    logging::{self, LogControl},
+    metrics,
+    moderation::{AutoMod, RaidGuard},
+    sampler::Sampler,
+    server::{self, GatewayReady, ServerState},
 };
-use serenity::{Client, all::GatewayIntents};
-use tracing_subscriber::{
-    EnvFilter,
-    fmt::{format::Writer, time::FormatTime},
+use serenity::{
+    Client,
+    all::{GatewayIntents, GuildId},
 };
+use tracing::{error, info};
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
 static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
-struct TimeFormatter {
-    offset: i32,
-}
-
-impl FormatTime for TimeFormatter {
-    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
-        let offset = self.offset;
-        let now = Utc::now().with_timezone(
-            &FixedOffset::east_opt(offset)
-                .expect("Failed to create FixedOffset with the configured time offset"),
-        );
-        write!(w, "{}", now.format("%Y-%m-%d %H:%M:%S%.3f %Z"))
+/// Re-read the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+async fn reload_on_hangup(cfg: Arc<ArcSwap<BotCfg>>, log: LogControl) -> Result<(), BotError> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        let new = match BotCfg::reload(&cfg) {
+            Ok(new) => new,
+            Err(e) => {
+                error!("Failed to reload configuration: {}", e);
+                continue;
+            }
+        };
+        info!("Configuration reloaded");
+        if let Err(e) = log.set_base(&logging::base_directive(new.log_level.as_deref())) {
+            error!("Failed to apply reloaded log level: {}", e);
+        }
     }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), BotError> {
-    let cfg = BotCfg::read(&Args::parse().config)?;
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_ansi(true)
-        .with_timer(TimeFormatter {
-            offset: cfg.time_offset,
-        })
-        .init();
+    let args = Args::parse();
+    match args.command.unwrap_or(Command::Run) {
+        Command::Run => run(&args.config, &args.db).await,
+        Command::Migrate => cli::migrate(&open(&args.config, &args.db).await?).await,
+        Command::CheckConfig => {
+            if !cli::check_config(&args.config)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::PrintSchema => cli::print_schema(),
+        Command::RegisterCommands { guild } => {
+            cli::register_commands(&BotCfg::read(&args.config)?, guild.map(GuildId::new)).await
+        }
+        Command::Db(command) => {
+            cli::db(&open(&args.config, &args.db).await?, &args.config, command).await
+        }
+        Command::Restore { backup } => cli::restore(&args.db, &backup).await,
+    }
+}
+
+/// Open the database for offline subcommands, which work without a valid configuration
+async fn open(config: &Path, db: &Path) -> Result<BotDatabase, BotError> {
+    connect(&BotCfg::load(config).unwrap_or_default(), db).await
+}
+
+/// `databaseUrl` when configured, the SQLite file at `db` otherwise
+async fn connect(cfg: &BotCfg, db: &Path) -> Result<BotDatabase, BotError> {
+    match &cfg.database_url {
+        Some(url) => BotDatabase::connect(url.expose(), &cfg.database).await,
+        None => BotDatabase::new(db, &cfg.database).await,
+    }
+}
+
+async fn run(config: &Path, db: &Path) -> Result<(), BotError> {
+    let cfg = BotCfg::read(config)?;
+    // Keep the guard alive so buffered file logs are flushed on exit
+    let (log, _log_guard) = logging::init(&cfg.logging, cfg.log_level.as_deref(), cfg.zone())?;
 
     let intents = GatewayIntents::non_privileged() | GatewayIntents::privileged();
 
-    let db = BotDatabase::new(&Args::parse().db).await?;
+    // `restore` refuses to swap the SQLite file while this is held
+    let _lock = match cfg.database_url {
+        Some(_) => None,
+        None => Some(RunLock::acquire(db)?),
+    };
+    let db = connect(&cfg, db).await?;
     let cfg = Arc::new(ArcSwap::from_pointee(cfg));
+    let sampler = Sampler::new();
+    let automod = AutoMod::new();
+    let ready = GatewayReady::default();
 
-    let mut client = Client::builder(cfg.load().token.to_owned(), intents)
+    let mut client = Client::builder(cfg.load().token.expose(), intents)
         .cache_settings({
             let mut s = serenity::cache::Settings::default();
             s.max_messages = 1000; // Set the maximum number of messages to cache
@@ -55,10 +108,52 @@ async fn main() -> Result<(), BotError> {
         })
         .type_map_insert::<BotDatabase>(db.to_owned())
         .type_map_insert::<BotCfg>(cfg.to_owned())
+        .type_map_insert::<AutoMod>(automod.to_owned())
+        .type_map_insert::<RaidGuard>(RaidGuard::new())
+        .type_map_insert::<GatewayReady>(ready.to_owned())
         .event_handler(PingHandler)
-        .framework(framework(db, cfg))
+        .event_handler(MessageTracker)
+        .event_handler(AutoModHandler)
+        .event_handler(RaidHandler)
+        .framework(framework(
+            db.to_owned(),
+            sampler.to_owned(),
+            log.to_owned(),
+            cfg.to_owned(),
+            automod,
+        ))
         .await?;
 
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(cfg.to_owned(), log));
+
+    sampler
+        .spawn(
+            client.shard_manager.to_owned(),
+            db.to_owned(),
+            cfg.load().persist_samples,
+        )
+        .await;
+
+    if let Some(backup) = cfg.load().backup.to_owned() {
+        database::backup::spawn(db.to_owned(), backup);
+    }
+
+    if let Some(addr) = cfg.load().http_addr {
+        let state = ServerState {
+            cache: client.cache.to_owned(),
+            shard_manager: client.shard_manager.to_owned(),
+            db: db.to_owned(),
+            metrics: metrics::install()?,
+            ready,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = server::serve(addr, state).await {
+                error!("HTTP server stopped: {}", e);
+            }
+        });
+    }
+
     // Finally, start a single shard, and start listening to events.
     //
     // Shards will automatically attempt to reconnect, and will perform exponential backoff until
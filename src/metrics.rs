@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use metrics::{
+    Unit, counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use serenity::{all::ShardManager, cache::Cache};
+use snafu::ResultExt;
+
+use crate::{database::BotDatabase, error::BotError};
+
+const COMMAND_DURATION: &str = "bot_command_duration_seconds";
+const COMMAND_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Install the global Prometheus recorder and describe all metrics
+pub fn install() -> Result<PrometheusHandle, BotError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(COMMAND_DURATION.into()), COMMAND_BUCKETS)
+        .whatever_context::<&str, BotError>("Failed to configure histogram buckets")?
+        .install_recorder()
+        .whatever_context::<&str, BotError>("Failed to install Prometheus recorder")?;
+
+    describe_counter!("bot_commands_total", "Command invocations by outcome");
+    describe_counter!("bot_command_errors_total", "Command errors by error kind");
+    describe_histogram!(COMMAND_DURATION, Unit::Seconds, "Command execution time");
+    describe_gauge!(
+        "bot_jemalloc_allocated_bytes",
+        Unit::Bytes,
+        "Bytes allocated by the bot"
+    );
+    describe_gauge!("bot_tokio_global_queue_depth", "Tasks in the global queue");
+    describe_gauge!("bot_tokio_alive_tasks", "Alive tokio tasks");
+    describe_gauge!("bot_tokio_workers", "Tokio worker threads");
+    describe_gauge!("bot_cache_users", "Cached users");
+    describe_gauge!("bot_cache_guilds", "Cached guilds");
+    describe_gauge!("bot_cache_channels", "Cached guild channels");
+    describe_gauge!("bot_database_size_bytes", Unit::Bytes, "Database size");
+    describe_gauge!(
+        "bot_database_latency_seconds",
+        Unit::Seconds,
+        "Round trip of a trivial database query"
+    );
+    describe_gauge!(
+        "bot_gateway_latency_seconds",
+        Unit::Seconds,
+        "Gateway heartbeat latency per shard"
+    );
+
+    Ok(handle)
+}
+
+/// Record a finished command invocation
+pub fn record_command(command: &str, elapsed: Duration, error_kind: Option<&str>) {
+    let outcome = if error_kind.is_some() {
+        "error"
+    } else {
+        "success"
+    };
+    counter!("bot_commands_total", "command" => command.to_owned(), "outcome" => outcome)
+        .increment(1);
+    histogram!(COMMAND_DURATION, "command" => command.to_owned()).record(elapsed.as_secs_f64());
+    if let Some(kind) = error_kind {
+        counter!(
+            "bot_command_errors_total",
+            "command" => command.to_owned(),
+            "kind" => kind.to_owned()
+        )
+        .increment(1);
+    }
+}
+
+/// Refresh the gauges that are sampled on scrape
+pub async fn collect(
+    cache: &Cache,
+    shard_manager: &ShardManager,
+    db: &BotDatabase,
+) -> Result<(), BotError> {
+    use tikv_jemalloc_ctl::{epoch, stats};
+    epoch::advance()?;
+    gauge!("bot_jemalloc_allocated_bytes").set(stats::allocated::read()? as f64);
+
+    let metrics = tokio::runtime::Handle::current().metrics();
+    gauge!("bot_tokio_global_queue_depth").set(metrics.global_queue_depth() as f64);
+    gauge!("bot_tokio_alive_tasks").set(metrics.num_alive_tasks() as f64);
+    gauge!("bot_tokio_workers").set(metrics.num_workers() as f64);
+
+    gauge!("bot_cache_users").set(cache.user_count() as f64);
+    gauge!("bot_cache_guilds").set(cache.guild_count() as f64);
+    gauge!("bot_cache_channels").set(cache.guild_channel_count() as f64);
+
+    for (id, runner) in shard_manager.runners.lock().await.iter() {
+        if let Some(latency) = runner.latency {
+            gauge!("bot_gateway_latency_seconds", "shard" => id.to_string())
+                .set(latency.as_secs_f64());
+        }
+    }
+
+    gauge!("bot_database_latency_seconds").set(db.latency().await?.as_secs_f64());
+    gauge!("bot_database_size_bytes").set(db.size().await? as f64);
+
+    Ok(())
+}
@@ -0,0 +1,472 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, LazyLock, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use regex::{Regex, RegexBuilder};
+use serenity::{
+    all::{ChannelId, GuildId, Message, UserId},
+    prelude::TypeMapKey,
+};
+use snafu::whatever;
+use tracing::warn;
+
+use crate::{database::BotDatabase, error::BotError, repo::RuleRecord};
+
+/// Identical messages older than this are forgotten, so it also caps the window of a rule
+pub const HISTORY: Duration = Duration::from_secs(10 * 60);
+/// Messages remembered per member for duplicate detection
+const HISTORY_LEN: usize = 20;
+/// Fewer cased letters than this never count as shouting
+const CAPS_MIN_LETTERS: usize = 10;
+/// Compiled size limit of a rule's regex, guards against patterns that blow up
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+static INVITE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:discord\.gg|discord(?:app)?\.com/invite)/[\w-]+").unwrap()
+});
+static MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<@[!&]?\d+>|@everyone|@here").unwrap());
+
+/// What a rule looks for in a message
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    /// Lowercased, ASCII words match whole words only, others any substring
+    Words(Vec<String>),
+    Regex(Regex),
+    Invites,
+    /// At least this many user, role or everyone mentions
+    Mentions(usize),
+    /// At least `count` identical messages from a member within `window`
+    Duplicates {
+        count: usize,
+        window: Duration,
+    },
+    /// At least this percentage of cased letters in upper case
+    Caps(u8),
+}
+
+/// What happens to a message that triggered a rule, it is deleted in every case
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleAction {
+    Delete,
+    Warn,
+    Timeout(Duration),
+    /// Repost the message in the toilet channel
+    Flush(ChannelId),
+}
+
+/// A validated rule, built from its database record
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: i32,
+    pub trigger: Trigger,
+    pub action: RuleAction,
+}
+
+/// The parts of a message rules look at
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sample<'a> {
+    pub content: &'a str,
+    pub mentions: usize,
+    /// How long ago the author sent the same content before
+    pub repeats: &'a [Duration],
+}
+
+impl<'a> Sample<'a> {
+    pub fn new(message: &'a Message, repeats: &'a [Duration]) -> Self {
+        Self {
+            content: &message.content,
+            mentions: message.mentions.len()
+                + message.mention_roles.len()
+                + message.mention_everyone as usize,
+            repeats,
+        }
+    }
+
+    /// Count mentions from the markup alone, for text that was never sent
+    pub fn from_text(content: &'a str) -> Self {
+        Self {
+            content,
+            mentions: MENTION.find_iter(content).count(),
+            repeats: &[],
+        }
+    }
+}
+
+/// Whether `word` appears in `text` on its own, both already lowercased
+fn contains_word(text: &str, word: &str) -> bool {
+    if !word.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return text.contains(word);
+    }
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// Split a comma or newline separated word list
+pub fn parse_words(pattern: &str) -> Vec<String> {
+    pattern
+        .split([',', '，', '\n'])
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+impl Trigger {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Trigger::Words(_) => "违禁词",
+            Trigger::Regex(_) => "正则表达式",
+            Trigger::Invites => "邀请链接",
+            Trigger::Mentions(_) => "提及刷屏",
+            Trigger::Duplicates { .. } => "重复刷屏",
+            Trigger::Caps(_) => "大写字母",
+        }
+    }
+
+    pub fn matches(&self, sample: &Sample) -> bool {
+        match self {
+            Trigger::Words(words) => {
+                let text = sample.content.to_lowercase();
+                words.iter().any(|w| contains_word(&text, w))
+            }
+            Trigger::Regex(regex) => regex.is_match(sample.content),
+            Trigger::Invites => INVITE.is_match(sample.content),
+            Trigger::Mentions(max) => sample.mentions >= *max,
+            // Images and stickers carry no text and would all look alike
+            Trigger::Duplicates { .. } if sample.content.trim().is_empty() => false,
+            Trigger::Duplicates { count, window } => {
+                1 + sample.repeats.iter().filter(|age| *age <= window).count() >= *count
+            }
+            Trigger::Caps(percent) => {
+                let (upper, letters) = sample
+                    .content
+                    .chars()
+                    .filter(|c| c.is_uppercase() || c.is_lowercase())
+                    .fold((0, 0), |(upper, letters), c| {
+                        (upper + c.is_uppercase() as usize, letters + 1)
+                    });
+                letters >= CAPS_MIN_LETTERS && upper * 100 >= letters * *percent as usize
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// Validate a stored rule, missing thresholds fall back to defaults
+    pub fn from_record(record: &RuleRecord) -> Result<Self, BotError> {
+        let threshold = |default: i64, min: i64, max: i64| -> Result<i64, BotError> {
+            let value = record.threshold.unwrap_or(default);
+            if !(min..=max).contains(&value) {
+                whatever!("Threshold must be between {min} and {max}, got {value}");
+            }
+            Ok(value)
+        };
+        let pattern = || -> Result<&str, BotError> {
+            match record.pattern.as_deref().map(str::trim) {
+                Some(pattern) if !pattern.is_empty() => Ok(pattern),
+                _ => whatever!("Rule `{}` needs a pattern", record.kind),
+            }
+        };
+        let trigger = match record.kind.as_str() {
+            "words" => {
+                let words = parse_words(pattern()?);
+                if words.is_empty() {
+                    whatever!("Rule `words` needs at least one word");
+                }
+                Trigger::Words(words)
+            }
+            "regex" => match RegexBuilder::new(pattern()?)
+                .case_insensitive(true)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+            {
+                Ok(regex) => Trigger::Regex(regex),
+                Err(e) => whatever!("Invalid regex: {e}"),
+            },
+            "invites" => Trigger::Invites,
+            "mentions" => Trigger::Mentions(threshold(5, 1, 100)? as usize),
+            "duplicates" => {
+                let window = record.window_secs.unwrap_or(30);
+                if !(1..=HISTORY.as_secs() as i64).contains(&window) {
+                    whatever!(
+                        "Window must be between 1 and {} seconds, got {window}",
+                        HISTORY.as_secs()
+                    );
+                }
+                Trigger::Duplicates {
+                    count: threshold(3, 2, HISTORY_LEN as i64)? as usize,
+                    window: Duration::from_secs(window as u64),
+                }
+            }
+            "caps" => Trigger::Caps(threshold(70, 1, 100)? as u8),
+            kind => whatever!("Unknown rule kind `{kind}`"),
+        };
+        let action = match record.action.as_str() {
+            "delete" => RuleAction::Delete,
+            "warn" => RuleAction::Warn,
+            "timeout" => match record.timeout_secs {
+                // Discord caps timeouts at 28 days
+                Some(secs @ 1..=2_419_200) => RuleAction::Timeout(Duration::from_secs(secs as u64)),
+                _ => whatever!("Action `timeout` needs a length of up to 28 days"),
+            },
+            "flush" => match record.toilet_id {
+                Some(id) if id > 0 => RuleAction::Flush(ChannelId::new(id as u64)),
+                _ => whatever!("Action `flush` needs a toilet channel"),
+            },
+            action => whatever!("Unknown rule action `{action}`"),
+        };
+        Ok(Self {
+            id: record.id,
+            trigger,
+            action,
+        })
+    }
+}
+
+/// Content hash and arrival of a member's recent messages, oldest first
+type Sent = VecDeque<(u64, Instant)>;
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Enabled rules per guild, loaded on first use
+    rules: RwLock<HashMap<GuildId, Arc<[Rule]>>>,
+    history: Mutex<HashMap<(GuildId, UserId), Sent>>,
+}
+
+/// Caches each guild's rules and remembers recent messages, cheap to clone
+#[derive(Debug, Clone, Default)]
+pub struct AutoMod(Arc<Inner>);
+
+impl TypeMapKey for AutoMod {
+    type Value = AutoMod;
+}
+
+impl AutoMod {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The enabled rules of `guild_id`, rules that no longer validate are skipped
+    pub async fn rules(
+        &self,
+        db: &BotDatabase,
+        guild_id: GuildId,
+    ) -> Result<Arc<[Rule]>, BotError> {
+        if let Some(rules) = self.0.rules.read().unwrap().get(&guild_id) {
+            return Ok(rules.clone());
+        }
+        let rules = db
+            .automod()
+            .list(guild_id)
+            .await?
+            .iter()
+            .filter(|record| record.enabled)
+            .filter_map(|record| match Rule::from_record(record) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    warn!(%guild_id, rule = record.id, "Skipping invalid automod rule: {}", e);
+                    None
+                }
+            })
+            .collect::<Arc<[Rule]>>();
+        self.0
+            .rules
+            .write()
+            .unwrap()
+            .insert(guild_id, rules.clone());
+        Ok(rules)
+    }
+
+    /// Drop the cached rules of `guild_id` after they changed
+    pub fn invalidate(&self, guild_id: GuildId) {
+        self.0.rules.write().unwrap().remove(&guild_id);
+    }
+
+    /// Remember a message, returning how long ago the author sent the same content before
+    ///
+    /// Messages without text are not remembered.
+    pub fn remember(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        content: &str,
+        now: Instant,
+    ) -> Vec<Duration> {
+        if content.trim().is_empty() {
+            return Vec::new();
+        }
+        let mut hasher = DefaultHasher::new();
+        content.trim().to_lowercase().hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut history = self.0.history.lock().unwrap();
+        let fresh = |at: &Instant| now.duration_since(*at) <= HISTORY;
+        // Forget members who went quiet once the map grows
+        if history.len() > 10_000 {
+            history.retain(|_, sent| sent.back().is_some_and(|(_, at)| fresh(at)));
+        }
+        let sent = history.entry((guild_id, user_id)).or_default();
+        sent.retain(|(_, at)| fresh(at));
+        let repeats = sent
+            .iter()
+            .filter(|(h, _)| *h == hash)
+            .map(|(_, at)| now.duration_since(*at))
+            .collect();
+        if sent.len() == HISTORY_LEN {
+            sent.pop_front();
+        }
+        sent.push_back((hash, now));
+        repeats
+    }
+}
+
+/// The first rule `sample` triggers
+pub fn first_match<'r>(rules: &'r [Rule], sample: &Sample) -> Option<&'r Rule> {
+    rules.iter().find(|rule| rule.trigger.matches(sample))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repo::test_rule;
+
+    fn rule(kind: &str, pattern: Option<&str>, threshold: Option<i64>) -> Rule {
+        Rule::from_record(&RuleRecord {
+            pattern: pattern.map(str::to_owned),
+            threshold,
+            ..test_rule(kind, "delete")
+        })
+        .unwrap()
+    }
+
+    fn text(content: &str) -> Sample<'_> {
+        Sample::from_text(content)
+    }
+
+    #[test]
+    fn test_words() {
+        let words = rule("words", Some("scam, Free Nitro，广告"), None).trigger;
+        assert!(words.matches(&text("this is a SCAM!")));
+        assert!(words.matches(&text("get free nitro here")));
+        assert!(words.matches(&text("这是广告")));
+        // Whole words only, so "scampi" is fine
+        assert!(!words.matches(&text("scampi for dinner")));
+        assert!(!words.matches(&text("hello")));
+    }
+
+    #[test]
+    fn test_regex_and_invites() {
+        let regex = rule("regex", Some(r"b[a4]d\s*w[o0]rd"), None).trigger;
+        assert!(regex.matches(&text("such a B4D W0RD")));
+        assert!(!regex.matches(&text("bad")));
+
+        let invites = rule("invites", None, None).trigger;
+        assert!(invites.matches(&text("join discord.gg/abc-123")));
+        assert!(invites.matches(&text("https://discord.com/invite/xyz")));
+        assert!(invites.matches(&text("https://discordapp.com/invite/xyz")));
+        assert!(!invites.matches(&text("https://discord.com/channels/1/2/3")));
+    }
+
+    #[test]
+    fn test_mentions_and_caps() {
+        let mentions = rule("mentions", None, Some(3)).trigger;
+        assert!(mentions.matches(&text("<@1> <@!2> <@&3>")));
+        assert!(mentions.matches(&text("@everyone <@1> <@2>")));
+        assert!(!mentions.matches(&text("<@1> <@2> <#3>")));
+
+        let caps = rule("caps", None, Some(70)).trigger;
+        assert!(caps.matches(&text("WHY IS NOBODY ANSWERING")));
+        assert!(!caps.matches(&text("Why is nobody answering")));
+        // Too short to be shouting, and CJK has no case
+        assert!(!caps.matches(&text("OK LOL")));
+        assert!(!caps.matches(&text("为什么没有人回答我的问题呢呢呢")));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let automod = AutoMod::new();
+        let (guild, user) = (GuildId::new(1), UserId::new(2));
+        let duplicates = Rule::from_record(&RuleRecord {
+            threshold: Some(3),
+            window_secs: Some(30),
+            ..test_rule("duplicates", "delete")
+        })
+        .unwrap()
+        .trigger;
+        let start = Instant::now();
+        let send = |content: &str, secs: u64| {
+            let repeats = automod.remember(guild, user, content, start + Duration::from_secs(secs));
+            duplicates.matches(&Sample {
+                content,
+                repeats: &repeats,
+                ..Default::default()
+            })
+        };
+        assert!(!send("buy now", 0));
+        assert!(!send("Buy now ", 5));
+        assert!(!send("something else", 6));
+        assert!(send("buy now", 10));
+        // The first two have left the window
+        assert!(!send("buy now", 39));
+        // Attachments and stickers without text never count as repeats
+        assert!(!send("", 40));
+        assert!(!send("  ", 41));
+        assert!(!send("", 42));
+        assert!(automod.remember(guild, user, "", start).is_empty());
+        // Other guilds keep their own history
+        assert!(
+            automod
+                .remember(GuildId::new(3), user, "buy now", start)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_first_match_and_validation() {
+        let rules = [
+            rule("invites", None, None),
+            Rule::from_record(&RuleRecord {
+                id: 2,
+                pattern: Some("spam".to_owned()),
+                timeout_secs: Some(600),
+                ..test_rule("words", "timeout")
+            })
+            .unwrap(),
+        ];
+        let matched = first_match(&rules, &text("spam spam")).unwrap();
+        assert_eq!(
+            matched.action,
+            RuleAction::Timeout(Duration::from_secs(600))
+        );
+        assert!(first_match(&rules, &text("hello")).is_none());
+
+        let invalid = [
+            test_rule("words", "delete"),
+            RuleRecord {
+                pattern: Some("(".to_owned()),
+                ..test_rule("regex", "delete")
+            },
+            RuleRecord {
+                threshold: Some(0),
+                ..test_rule("mentions", "delete")
+            },
+            RuleRecord {
+                window_secs: Some(3600),
+                ..test_rule("duplicates", "delete")
+            },
+            test_rule("invites", "timeout"),
+            test_rule("invites", "flush"),
+            test_rule("links", "delete"),
+        ];
+        for record in &invalid {
+            assert!(Rule::from_record(record).is_err(), "{record:?}");
+        }
+    }
+}
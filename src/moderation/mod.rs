@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use serenity::all::{
+    colours::branding::{FUCHSIA, GREEN, RED, YELLOW},
+    *,
+};
+use tracing::warn;
+
+pub use self::{
+    automod::{AutoMod, Rule, RuleAction, Sample, Trigger, first_match},
+    raid::{Lockdown, RaidGuard, Surge, SurgeKind},
+};
+use crate::{
+    config::BotCfg,
+    database::BotDatabase,
+    error::BotError,
+    repo::{Case, CaseAction},
+};
+
+pub mod automod;
+pub mod raid;
+
+/// Title and colour of a case
+pub fn style(action: CaseAction) -> (&'static str, Colour) {
+    match action {
+        CaseAction::Warn => ("⚠️ 警告", YELLOW),
+        CaseAction::Timeout => ("🔇 禁言", FUCHSIA),
+        CaseAction::Kick => ("👢 踢出", Colour::ORANGE),
+        CaseAction::Ban => ("🔨 封禁", RED),
+        CaseAction::Unban => ("🕊️ 解封", GREEN),
+    }
+}
+
+/// `90061s` as `1d 1h 1m 1s`
+pub fn human(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts = [
+        (secs / 86400, "d"),
+        (secs / 3600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+    ]
+    .iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, unit)| format!("{n}{unit}"))
+    .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+pub fn case_embed(case: &Case) -> Result<CreateEmbed, BotError> {
+    let (title, colour) = style(case.action.parse()?);
+    let mut embed = CreateEmbed::new()
+        .title(format!("{title} · 案例 #{}", case.case_number))
+        .color(colour)
+        .field(
+            "对象",
+            format!("<@{}> `{}`", case.user_id, case.user_id),
+            true,
+        )
+        .field("执行者", format!("<@{}>", case.moderator_id), true);
+    if let Some(secs) = case.duration_secs {
+        embed = embed.field("时长", human(Duration::from_secs(secs as u64)), true);
+    }
+    embed = embed
+        .field("原因", case.reason.as_deref().unwrap_or("未填写"), false)
+        .timestamp(case.created_at.to_utc());
+    if case.updated_at.is_some() {
+        embed = embed.footer(CreateEmbedFooter::new("原因已编辑"));
+    }
+    Ok(embed)
+}
+
+/// Post to the guild's mod-log channel, when one is configured
+pub async fn post_log(http: impl CacheHttp, cfg: &BotCfg, guild_id: GuildId, embed: CreateEmbed) {
+    let Some(&channel) = cfg.moderation.log_channels.get(&guild_id) else {
+        return;
+    };
+    if let Err(e) = channel
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await
+    {
+        warn!(%guild_id, %channel, "Failed to post to the mod log: {}", e);
+    }
+}
+
+/// Tell the target what happened in `guild`, returning whether the DM went through
+pub async fn notify(
+    http: impl CacheHttp,
+    user_id: UserId,
+    guild: &str,
+    action: CaseAction,
+    reason: Option<&str>,
+    duration: Option<Duration>,
+) -> bool {
+    let (title, colour) = style(action);
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .color(colour)
+        .description(format!("你在 **{guild}** 收到了一项管理处理。"))
+        .field("原因", reason.unwrap_or("未填写"), false)
+        .timestamp(Timestamp::now());
+    if let Some(duration) = duration {
+        embed = embed.field("时长", human(duration), true);
+    }
+    user_id
+        .direct_message(http, CreateMessage::new().embed(embed))
+        .await
+        .is_ok()
+}
+
+/// Take back a notification sent ahead of an action that then failed
+pub async fn retract(
+    http: impl CacheHttp,
+    user_id: UserId,
+    guild: &str,
+    action: CaseAction,
+) -> bool {
+    let (title, _) = style(action);
+    let embed = CreateEmbed::new()
+        .title(format!("{title} (已撤回)"))
+        .description(format!(
+            "此前在 **{guild}** 通知你的处理未能执行，请忽略上一条消息。"
+        ))
+        .timestamp(Timestamp::now());
+    user_id
+        .direct_message(http, CreateMessage::new().embed(embed))
+        .await
+        .is_ok()
+}
+
+/// Record a case and post it to the mod log
+#[allow(clippy::too_many_arguments)]
+pub async fn open_case(
+    http: impl CacheHttp,
+    cfg: &BotCfg,
+    db: &BotDatabase,
+    guild_id: GuildId,
+    action: CaseAction,
+    user_id: UserId,
+    moderator_id: UserId,
+    reason: Option<&str>,
+    duration: Option<Duration>,
+) -> Result<Case, BotError> {
+    let case = db
+        .case()
+        .create(guild_id, action, user_id, moderator_id, reason, duration)
+        .await?;
+    post_log(http, cfg, guild_id, case_embed(&case)?).await;
+    Ok(case)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_human_duration() {
+        assert_eq!(human(Duration::ZERO), "0s");
+        assert_eq!(human(Duration::from_secs(600)), "10m");
+        assert_eq!(human(Duration::from_secs(90061)), "1d 1h 1m 1s");
+    }
+}
@@ -0,0 +1,473 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serenity::{
+    all::{
+        CacheHttp, ChannelId, ChannelType, EditChannel, EditGuild, Guild, GuildId, Timestamp,
+        UserId, VerificationLevel,
+    },
+    prelude::TypeMapKey,
+};
+use snafu::OptionExt;
+use tracing::warn;
+
+use crate::{config::RaidCfg, database::BotDatabase, error::BotError, repo::LockdownRecord};
+
+/// Guild feature that stops new members from joining through invites
+const INVITES_DISABLED: &str = "INVITES_DISABLED";
+
+/// Which threshold a burst of joins crossed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurgeKind {
+    Joins,
+    NewAccounts,
+}
+
+/// A burst of joins, with everyone who joined within the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Surge {
+    pub kind: SurgeKind,
+    pub members: Vec<UserId>,
+    pub new_accounts: usize,
+}
+
+/// What a lockdown changed, so lifting it can put things back
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lockdown {
+    pub since: DateTime<Utc>,
+    /// `None` when the bot locked down on its own
+    pub by: Option<UserId>,
+    /// Verification level before the lockdown, `None` when it was already high enough
+    pub verification: Option<VerificationLevel>,
+    /// Whether the lockdown paused invites, they may have been paused already
+    pub invites_paused: bool,
+    /// Text channels and their slowmode before the lockdown
+    pub slowmodes: Vec<(ChannelId, u16)>,
+}
+
+#[derive(Debug, Default)]
+struct GuildState {
+    /// Arrival, member and whether the account is new, oldest first
+    joins: VecDeque<(Instant, UserId, bool)>,
+    /// Last time a surge was reported
+    alerted: Option<Instant>,
+}
+
+/// Tracks recent joins of each guild, cheap to clone
+#[derive(Debug, Clone, Default)]
+pub struct RaidGuard(Arc<Mutex<HashMap<GuildId, GuildState>>>);
+
+impl TypeMapKey for RaidGuard {
+    type Value = RaidGuard;
+}
+
+/// Whether the account behind `user_id` is younger than `days`
+pub fn is_new_account(user_id: UserId, now: Timestamp, days: u32) -> bool {
+    now.unix_timestamp() - user_id.created_at().unix_timestamp() < days as i64 * 24 * 60 * 60
+}
+
+impl RaidGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a join, returning the surge it completes
+    ///
+    /// A surge is reported once per window.
+    pub fn record(
+        &self,
+        guild_id: GuildId,
+        cfg: &RaidCfg,
+        now: Instant,
+        user_id: UserId,
+        new_account: bool,
+    ) -> Option<Surge> {
+        let window = Duration::from_secs(cfg.window_secs);
+        let mut guilds = self.0.lock().unwrap();
+        let state = guilds.entry(guild_id).or_default();
+        state
+            .joins
+            .retain(|(at, ..)| now.duration_since(*at) <= window);
+        state.joins.push_back((now, user_id, new_account));
+
+        let quiet = state
+            .alerted
+            .is_none_or(|at| now.duration_since(at) > window);
+        if !quiet {
+            return None;
+        }
+        let new_accounts = state.joins.iter().filter(|(.., new)| *new).count();
+        let kind = if state.joins.len() >= cfg.joins as usize {
+            SurgeKind::Joins
+        } else if new_accounts >= cfg.new_accounts as usize {
+            SurgeKind::NewAccounts
+        } else {
+            return None;
+        };
+        state.alerted = Some(now);
+        Some(Surge {
+            kind,
+            members: state.joins.iter().map(|(_, id, _)| *id).collect(),
+            new_accounts,
+        })
+    }
+}
+
+impl Lockdown {
+    /// Row stored for the lockdown of `guild_id`, slowmodes as `channel:secs` pairs
+    pub fn to_record(&self, guild_id: GuildId) -> LockdownRecord {
+        LockdownRecord {
+            guild_id: guild_id.get() as i64,
+            started_at: self.since.fixed_offset(),
+            started_by: self.by.map(|id| id.get() as i64),
+            verification: self.verification.map(|level| u8::from(level) as i32),
+            invites_paused: self.invites_paused,
+            slowmodes: self
+                .slowmodes
+                .iter()
+                .map(|(id, secs)| format!("{id}:{secs}"))
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+
+    pub fn from_record(record: &LockdownRecord) -> Result<Self, BotError> {
+        let slowmodes = record
+            .slowmodes
+            .split(',')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (id, secs) = pair.split_once(':')?;
+                Some((ChannelId::new(id.parse().ok()?), secs.parse().ok()?))
+            })
+            .collect::<Option<Vec<_>>>()
+            .with_whatever_context::<_, String, BotError>(|| {
+                format!("Invalid lockdown slowmodes `{}`", record.slowmodes)
+            })?;
+        Ok(Lockdown {
+            since: record.started_at.to_utc(),
+            by: record.started_by.map(|id| UserId::new(id as u64)),
+            verification: record
+                .verification
+                .map(|level| VerificationLevel::from(level as u8)),
+            invites_paused: record.invites_paused,
+            slowmodes,
+        })
+    }
+}
+
+/// The lockdown `guild_id` is under, if any
+pub async fn lockdown(db: &BotDatabase, guild_id: GuildId) -> Result<Option<Lockdown>, BotError> {
+    db.lockdown()
+        .get(guild_id)
+        .await?
+        .as_ref()
+        .map(Lockdown::from_record)
+        .transpose()
+}
+
+/// Lock `guild` down, returning the lockdown and how many changes failed
+///
+/// Returns `None` without changing anything when the guild is already locked down.
+pub async fn lock(
+    http: impl CacheHttp,
+    db: &BotDatabase,
+    guild: &Guild,
+    cfg: &RaidCfg,
+    by: Option<UserId>,
+) -> Result<Option<(Lockdown, usize)>, BotError> {
+    let lockdown = plan(guild, cfg, by);
+    if !db.lockdown().begin(lockdown.to_record(guild.id)).await? {
+        return Ok(None);
+    }
+    let failed = apply(http, guild, &lockdown, cfg).await;
+    Ok(Some((lockdown, failed)))
+}
+
+/// Lift the lockdown of `guild`, returning it, whether it was recorded and how many changes
+/// failed
+///
+/// Returns `None` without changing anything when no lockdown is recorded, unless `force` is set,
+/// which undoes what a lockdown most likely changed instead.
+pub async fn unlock(
+    http: impl CacheHttp,
+    db: &BotDatabase,
+    guild: &Guild,
+    cfg: &RaidCfg,
+    force: bool,
+) -> Result<Option<(Lockdown, bool, usize)>, BotError> {
+    let (lockdown, recorded) = match db.lockdown().end(guild.id).await? {
+        Some(record) => (Lockdown::from_record(&record)?, true),
+        None if force => (fallback(guild, cfg), false),
+        None => return Ok(None),
+    };
+    let failed = lift(http, guild, &lockdown).await;
+    Ok(Some((lockdown, recorded, failed)))
+}
+
+/// What locking `guild` down changes, given its current settings
+fn plan(guild: &Guild, cfg: &RaidCfg, by: Option<UserId>) -> Lockdown {
+    let mut slowmodes = guild
+        .channels
+        .values()
+        .filter(|c| c.kind == ChannelType::Text)
+        .filter_map(|c| {
+            let current = c.rate_limit_per_user.unwrap_or(0);
+            (current < cfg.slowmode_secs).then_some((c.id, current))
+        })
+        .collect::<Vec<_>>();
+    slowmodes.sort();
+    Lockdown {
+        since: Utc::now(),
+        by,
+        verification: (guild.verification_level < VerificationLevel::High)
+            .then_some(guild.verification_level),
+        invites_paused: !guild.features.iter().any(|f| f == INVITES_DISABLED),
+        slowmodes,
+    }
+}
+
+/// What lifting a lockdown that was never recorded should undo
+///
+/// The previous verification level is unknown, so it stays. Channels still at the lockdown
+/// slowmode lose it.
+fn fallback(guild: &Guild, cfg: &RaidCfg) -> Lockdown {
+    let mut slowmodes = guild
+        .channels
+        .values()
+        .filter(|c| {
+            c.kind == ChannelType::Text
+                && cfg.slowmode_secs > 0
+                && c.rate_limit_per_user == Some(cfg.slowmode_secs)
+        })
+        .map(|c| (c.id, 0))
+        .collect::<Vec<_>>();
+    slowmodes.sort();
+    Lockdown {
+        since: Utc::now(),
+        by: None,
+        verification: None,
+        invites_paused: guild.features.iter().any(|f| f == INVITES_DISABLED),
+        slowmodes,
+    }
+}
+
+/// One line per change a lockdown makes, `slowmode_secs` is the lockdown slowmode
+pub fn describe(lockdown: &Lockdown, slowmode_secs: u16) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(level) = lockdown.verification {
+        lines.push(format!("验证等级: {level:?} → High"));
+    }
+    if lockdown.invites_paused {
+        lines.push("暂停邀请".to_string());
+    }
+    if !lockdown.slowmodes.is_empty() {
+        lines.push(format!(
+            "{} 个文字频道慢速模式 {slowmode_secs}s",
+            lockdown.slowmodes.len()
+        ));
+    }
+    if lines.is_empty() {
+        lines.push("无需更改".to_string());
+    }
+    lines
+}
+
+/// Features of `guild` with invites paused or resumed
+fn features(guild: &Guild, paused: bool) -> Vec<String> {
+    let mut features = guild
+        .features
+        .iter()
+        .filter(|f| *f != INVITES_DISABLED)
+        .cloned()
+        .collect::<Vec<_>>();
+    if paused {
+        features.push(INVITES_DISABLED.to_string());
+    }
+    features
+}
+
+/// Set the slowmode of each channel, returning how many edits failed
+async fn set_slowmodes(
+    http: impl CacheHttp,
+    channels: impl Iterator<Item = (ChannelId, u16)>,
+) -> usize {
+    let mut failed = 0;
+    for (channel, secs) in channels {
+        if let Err(e) = channel
+            .edit(&http, EditChannel::new().rate_limit_per_user(secs))
+            .await
+        {
+            failed += 1;
+            warn!(%channel, "Failed to set slowmode: {}", e);
+        }
+    }
+    failed
+}
+
+/// Carry out `lockdown` on `guild`, returning how many changes failed
+async fn apply(http: impl CacheHttp, guild: &Guild, lockdown: &Lockdown, cfg: &RaidCfg) -> usize {
+    let mut failed = 0;
+    if lockdown.verification.is_some() || lockdown.invites_paused {
+        let mut edit = EditGuild::new().audit_log_reason("Raid lockdown");
+        if lockdown.verification.is_some() {
+            edit = edit.verification_level(VerificationLevel::High);
+        }
+        if lockdown.invites_paused {
+            edit = edit.features(features(guild, true));
+        }
+        if let Err(e) = guild.id.edit(&http, edit).await {
+            failed += 1;
+            warn!(guild_id = %guild.id, "Failed to lock guild settings: {}", e);
+        }
+    }
+    let channels = lockdown
+        .slowmodes
+        .iter()
+        .map(|(id, _)| (*id, cfg.slowmode_secs));
+    failed + set_slowmodes(&http, channels).await
+}
+
+/// Undo `lockdown` on `guild`, returning how many changes failed
+async fn lift(http: impl CacheHttp, guild: &Guild, lockdown: &Lockdown) -> usize {
+    let mut failed = 0;
+    if lockdown.verification.is_some() || lockdown.invites_paused {
+        let mut edit = EditGuild::new().audit_log_reason("Raid lockdown lifted");
+        if let Some(level) = lockdown.verification {
+            edit = edit.verification_level(level);
+        }
+        if lockdown.invites_paused {
+            edit = edit.features(features(guild, false));
+        }
+        if let Err(e) = guild.id.edit(&http, edit).await {
+            failed += 1;
+            warn!(guild_id = %guild.id, "Failed to restore guild settings: {}", e);
+        }
+    }
+    failed + set_slowmodes(&http, lockdown.slowmodes.iter().copied()).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::{test_channel, test_snowflake};
+
+    #[test]
+    fn test_join_bursts() {
+        let guard = RaidGuard::new();
+        let guild = GuildId::new(1);
+        let cfg = RaidCfg {
+            joins: 3,
+            new_accounts: 2,
+            window_secs: 10,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let join = |secs: u64, id: u64, new: bool| {
+            guard.record(
+                guild,
+                &cfg,
+                start + Duration::from_secs(secs),
+                UserId::new(id),
+                new,
+            )
+        };
+        assert_eq!(join(0, 1, false), None);
+        assert_eq!(join(5, 2, false), None);
+        // The first join has left the window
+        assert_eq!(join(11, 3, false), None);
+        let surge = join(12, 4, false).unwrap();
+        assert_eq!(surge.kind, SurgeKind::Joins);
+        assert_eq!(surge.members, [2, 3, 4].map(UserId::new));
+        // Reported once per window
+        assert_eq!(join(13, 5, false), None);
+
+        // Two new accounts are enough even though the join threshold isn't reached
+        let other = GuildId::new(2);
+        assert_eq!(guard.record(other, &cfg, start, UserId::new(6), true), None);
+        let surge = guard
+            .record(other, &cfg, start, UserId::new(7), true)
+            .unwrap();
+        assert_eq!(
+            (surge.kind, surge.new_accounts),
+            (SurgeKind::NewAccounts, 2)
+        );
+    }
+
+    #[test]
+    fn test_new_account() {
+        let now = Timestamp::now();
+        let id_at = |secs_ago| UserId::new(test_snowflake(now, secs_ago));
+        assert!(is_new_account(id_at(60 * 60), now, 7));
+        assert!(!is_new_account(id_at(8 * 24 * 60 * 60), now, 7));
+    }
+
+    #[test]
+    fn test_plan_and_fallback() {
+        let mut guild = Guild::default();
+        guild.verification_level = VerificationLevel::Low;
+        guild.features = vec!["COMMUNITY".to_string()];
+        let channel = |id: u64, kind: ChannelType, slowmode: Option<u16>| {
+            let mut channel = test_channel(id, kind, None, 0);
+            channel.rate_limit_per_user = slowmode;
+            channel
+        };
+        for c in [
+            channel(1, ChannelType::Text, None),
+            channel(2, ChannelType::Text, Some(60)),
+            channel(3, ChannelType::Voice, None),
+            channel(4, ChannelType::Text, Some(30)),
+        ] {
+            guild.channels.insert(c.id, c);
+        }
+        let cfg = RaidCfg::default();
+        let lockdown = plan(&guild, &cfg, None);
+        assert_eq!(lockdown.verification, Some(VerificationLevel::Low));
+        assert!(lockdown.invites_paused);
+        // Channels already as slow as the lockdown are left alone
+        assert_eq!(lockdown.slowmodes, vec![(ChannelId::new(1), 0)]);
+        assert_eq!(features(&guild, true), ["COMMUNITY", INVITES_DISABLED]);
+
+        guild.verification_level = VerificationLevel::Higher;
+        guild.features = features(&guild, true);
+        assert_eq!(plan(&guild, &cfg, None).verification, None);
+        let fallback = fallback(&guild, &cfg);
+        assert!(fallback.invites_paused);
+        assert_eq!(fallback.slowmodes, vec![(ChannelId::new(4), 0)]);
+        assert_eq!(features(&guild, false), ["COMMUNITY"]);
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let lockdown = Lockdown {
+            since: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            by: Some(UserId::new(2)),
+            verification: Some(VerificationLevel::Medium),
+            invites_paused: true,
+            slowmodes: vec![(ChannelId::new(3), 0), (ChannelId::new(4), 5)],
+        };
+        let record = lockdown.to_record(GuildId::new(1));
+        assert_eq!(record.slowmodes, "3:0,4:5");
+        assert_eq!(Lockdown::from_record(&record).unwrap(), lockdown);
+
+        let empty = Lockdown {
+            by: None,
+            verification: None,
+            slowmodes: vec![],
+            ..lockdown
+        };
+        let record = empty.to_record(GuildId::new(1));
+        assert_eq!(Lockdown::from_record(&record).unwrap(), empty);
+        assert!(
+            Lockdown::from_record(&LockdownRecord {
+                slowmodes: "3".to_owned(),
+                ..record
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use entities::automod_rules::*;
+use sea_orm::{ActiveValue::NotSet, IntoActiveModel, QueryOrder, prelude::*};
+use serenity::all::*;
+
+use crate::{database::BotDatabase, error::BotError};
+
+pub type RuleRecord = Model;
+
+pub struct AutomodRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn automod(&self) -> AutomodRepo<'_> {
+        AutomodRepo(self)
+    }
+}
+
+impl AutomodRepo<'_> {
+    /// Store a new rule, the `id` of `rule` is ignored
+    pub async fn add(&self, rule: RuleRecord) -> Result<RuleRecord, BotError> {
+        let mut rule = rule.into_active_model();
+        rule.id = NotSet;
+        Ok(rule.insert(self.0.inner()).await?)
+    }
+
+    /// Every rule of `guild_id` in creation order, enabled or not
+    pub async fn list(&self, guild_id: GuildId) -> Result<Vec<RuleRecord>, BotError> {
+        Ok(Entity::find()
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .order_by_asc(Column::Id)
+            .all(self.0.inner())
+            .await?)
+    }
+
+    /// Delete a rule, returning whether it existed
+    pub async fn remove(&self, guild_id: GuildId, id: i32) -> Result<bool, BotError> {
+        let result = Entity::delete_many()
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .filter(Column::Id.eq(id))
+            .exec(self.0.inner())
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Turn a rule on or off, returning whether it exists
+    pub async fn set_enabled(
+        &self,
+        guild_id: GuildId,
+        id: i32,
+        enabled: bool,
+    ) -> Result<bool, BotError> {
+        let result = Entity::update_many()
+            .col_expr(Column::Enabled, Expr::value(enabled))
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .filter(Column::Id.eq(id))
+            .exec(self.0.inner())
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+/// Fields shared by the tests, `kind` and `action` are filled in by each
+#[cfg(test)]
+pub(crate) fn test_rule(kind: &str, action: &str) -> RuleRecord {
+    RuleRecord {
+        id: 0,
+        guild_id: 1,
+        kind: kind.to_owned(),
+        pattern: None,
+        threshold: None,
+        window_secs: None,
+        action: action.to_owned(),
+        timeout_secs: None,
+        toilet_id: None,
+        enabled: true,
+        created_by: 2,
+        created_at: chrono::Utc::now().fixed_offset(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_rules_per_guild() {
+        for_each_backend(async |db| {
+            let repo = db.automod();
+            let guild = GuildId::new(1);
+            let words = repo
+                .add(RuleRecord {
+                    pattern: Some("spam, scam".to_owned()),
+                    ..test_rule("words", "delete")
+                })
+                .await
+                .unwrap();
+            let invites = repo.add(test_rule("invites", "warn")).await.unwrap();
+            repo.add(RuleRecord {
+                guild_id: 2,
+                ..test_rule("caps", "delete")
+            })
+            .await
+            .unwrap();
+
+            let rules = repo.list(guild).await.unwrap();
+            assert_eq!(rules, vec![words.clone(), invites.clone()]);
+
+            assert!(repo.set_enabled(guild, invites.id, false).await.unwrap());
+            assert!(!repo.list(guild).await.unwrap()[1].enabled);
+            // Rules of other guilds can't be touched
+            assert!(!repo.remove(GuildId::new(2), words.id).await.unwrap());
+            assert!(repo.remove(guild, words.id).await.unwrap());
+            assert!(!repo.remove(guild, words.id).await.unwrap());
+            assert_eq!(repo.list(guild).await.unwrap().len(), 1);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,259 @@
+use std::{fmt, time::Duration};
+
+use chrono::Utc;
+use entities::mod_cases::*;
+use sea_orm::{
+    FromQueryResult, IntoActiveModel, QueryOrder, Set, SqlErr,
+    prelude::*,
+    sea_query::{Expr, Func, Query},
+};
+use serenity::all::*;
+use snafu::{ResultExt, whatever};
+
+use crate::{database::BotDatabase, error::BotError};
+
+pub type Case = Model;
+
+/// What a moderator did, stored by name in `mod_cases.action`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseAction {
+    Warn,
+    Timeout,
+    Kick,
+    Ban,
+    Unban,
+}
+
+impl CaseAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CaseAction::Warn => "warn",
+            CaseAction::Timeout => "timeout",
+            CaseAction::Kick => "kick",
+            CaseAction::Ban => "ban",
+            CaseAction::Unban => "unban",
+        }
+    }
+}
+
+impl fmt::Display for CaseAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CaseAction {
+    type Err = BotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "warn" => CaseAction::Warn,
+            "timeout" => CaseAction::Timeout,
+            "kick" => CaseAction::Kick,
+            "ban" => CaseAction::Ban,
+            "unban" => CaseAction::Unban,
+            _ => whatever!("Unknown case action `{s}`"),
+        })
+    }
+}
+
+/// Inserts tried before a case number conflict is given up on, each loss means another
+/// moderator's case went in
+const CREATE_ATTEMPTS: usize = 10;
+
+pub struct CaseRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn case(&self) -> CaseRepo<'_> {
+        CaseRepo(self)
+    }
+}
+
+impl CaseRepo<'_> {
+    /// Open a case with the next number in `guild_id`
+    ///
+    /// The number is computed in the same statement as the insert. SQLite serializes writers,
+    /// but on Postgres two concurrent inserts can read the same maximum; the unique index
+    /// rejects the later one, which is retried with a fresh snapshot.
+    pub async fn create(
+        &self,
+        guild_id: GuildId,
+        action: CaseAction,
+        user_id: UserId,
+        moderator_id: UserId,
+        reason: Option<&str>,
+        duration: Option<Duration>,
+    ) -> Result<Case, BotError> {
+        let guild = guild_id.get() as i64;
+        let next = Query::select()
+            .expr(Expr::val(guild))
+            .expr(
+                Expr::expr(Func::coalesce([
+                    Expr::col(Column::CaseNumber).max(),
+                    Expr::val(0).into(),
+                ]))
+                .add(1),
+            )
+            .expr(Expr::val(action.as_str()))
+            .expr(Expr::val(user_id.get() as i64))
+            .expr(Expr::val(moderator_id.get() as i64))
+            .expr(Expr::val(reason.map(str::to_owned)))
+            .expr(Expr::val(duration.map(|d| d.as_secs() as i64)))
+            .expr(Expr::val(Utc::now().fixed_offset()))
+            .from(Entity)
+            .and_where(Column::GuildId.eq(guild))
+            .to_owned();
+        let mut insert = Query::insert()
+            .into_table(Entity)
+            .columns([
+                Column::GuildId,
+                Column::CaseNumber,
+                Column::Action,
+                Column::UserId,
+                Column::ModeratorId,
+                Column::Reason,
+                Column::DurationSecs,
+                Column::CreatedAt,
+            ])
+            .select_from(next)
+            .whatever_context::<&str, BotError>("Failed to build case insert")?
+            .to_owned();
+        insert.returning_all();
+
+        let db = self.0.inner();
+        let stmt = db.get_database_backend().build(&insert);
+        let mut attempts = 1;
+        let row = loop {
+            match db.query_one(stmt.to_owned()).await {
+                Err(e)
+                    if attempts < CREATE_ATTEMPTS
+                        && matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) =>
+                {
+                    attempts += 1;
+                }
+                result => break result?,
+            }
+        };
+        match row {
+            Some(row) => Ok(Model::from_query_result(&row, "")?),
+            None => whatever!("Failed to create case"),
+        }
+    }
+
+    /// Case `number` of `guild_id`
+    pub async fn get(&self, guild_id: GuildId, number: i64) -> Result<Option<Case>, BotError> {
+        Ok(Entity::find()
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .filter(Column::CaseNumber.eq(number))
+            .one(self.0.inner())
+            .await?)
+    }
+
+    /// Replace the reason of a case, `None` when there is no such case
+    pub async fn set_reason(
+        &self,
+        guild_id: GuildId,
+        number: i64,
+        reason: &str,
+    ) -> Result<Option<Case>, BotError> {
+        let Some(case) = self.get(guild_id, number).await? else {
+            return Ok(None);
+        };
+        let mut case = case.into_active_model();
+        case.reason = Set(Some(reason.to_owned()));
+        case.updated_at = Set(Some(Utc::now().fixed_offset()));
+        Ok(Some(case.update(self.0.inner()).await?))
+    }
+
+    /// Cases against `user_id` in `guild_id`, newest first
+    pub async fn list(&self, guild_id: GuildId, user_id: UserId) -> Result<Vec<Case>, BotError> {
+        Ok(Entity::find()
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .filter(Column::UserId.eq(user_id.get() as i64))
+            .order_by_desc(Column::CaseNumber)
+            .all(self.0.inner())
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_concurrent_cases_get_distinct_numbers() {
+        for_each_backend(async |db| {
+            let repo = db.case();
+            let guild = GuildId::new(1);
+            let cases = futures::future::join_all((0..8).map(|i| {
+                repo.create(
+                    guild,
+                    CaseAction::Warn,
+                    UserId::new(10 + i),
+                    UserId::new(20),
+                    None,
+                    None,
+                )
+            }))
+            .await;
+            let mut numbers = cases
+                .into_iter()
+                .map(|case| case.unwrap().case_number)
+                .collect::<Vec<_>>();
+            numbers.sort();
+            assert_eq!(numbers, (1..=8).collect::<Vec<_>>());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_cases_are_numbered_per_guild() {
+        for_each_backend(async |db| {
+            let repo = db.case();
+            let (a, b) = (GuildId::new(1), GuildId::new(2));
+            let (user, other, moderator) = (UserId::new(10), UserId::new(11), UserId::new(20));
+
+            let first = repo
+                .create(a, CaseAction::Warn, user, moderator, Some("spam"), None)
+                .await
+                .unwrap();
+            let timeout = Some(Duration::from_secs(600));
+            let second = repo
+                .create(a, CaseAction::Timeout, user, moderator, None, timeout)
+                .await
+                .unwrap();
+            let elsewhere = repo
+                .create(b, CaseAction::Ban, other, moderator, None, None)
+                .await
+                .unwrap();
+            assert_eq!(
+                (first.case_number, second.case_number, elsewhere.case_number),
+                (1, 2, 1)
+            );
+            assert_eq!(
+                second.action.parse::<CaseAction>().unwrap(),
+                CaseAction::Timeout
+            );
+            assert_eq!(second.duration_secs, Some(600));
+            assert_eq!(first.reason.as_deref(), Some("spam"));
+
+            let edited = repo.set_reason(a, 2, "flooding").await.unwrap().unwrap();
+            assert_eq!(edited.reason.as_deref(), Some("flooding"));
+            assert!(edited.updated_at.is_some());
+            assert!(repo.set_reason(a, 3, "nope").await.unwrap().is_none());
+
+            let history = repo.list(a, user).await.unwrap();
+            assert_eq!(
+                history.iter().map(|c| c.case_number).collect::<Vec<_>>(),
+                vec![2, 1]
+            );
+            assert!(repo.list(b, user).await.unwrap().is_empty());
+            assert_eq!(
+                repo.get(b, 1).await.unwrap().unwrap().user_id,
+                other.get() as i64
+            );
+        })
+        .await;
+    }
+}
@@ -0,0 +1,89 @@
+use entities::lockdowns::*;
+use sea_orm::{IntoActiveModel, SqlErr, prelude::*};
+use serenity::all::*;
+
+use crate::{database::BotDatabase, error::BotError};
+
+pub type LockdownRecord = Model;
+
+pub struct LockdownRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn lockdown(&self) -> LockdownRepo<'_> {
+        LockdownRepo(self)
+    }
+}
+
+impl LockdownRepo<'_> {
+    /// The lockdown `guild_id` is under, if any
+    pub async fn get(&self, guild_id: GuildId) -> Result<Option<LockdownRecord>, BotError> {
+        Ok(Entity::find_by_id(guild_id.get() as i64)
+            .one(self.0.inner())
+            .await?)
+    }
+
+    /// Store a lockdown, returning `false` when the guild is already locked down
+    pub async fn begin(&self, lockdown: LockdownRecord) -> Result<bool, BotError> {
+        match lockdown.into_active_model().insert(self.0.inner()).await {
+            Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                Ok(false)
+            }
+            result => result.map(|_| true).map_err(Into::into),
+        }
+    }
+
+    /// Delete the lockdown of `guild_id`, returning it
+    ///
+    /// Only one of several concurrent calls gets the lockdown back.
+    pub async fn end(&self, guild_id: GuildId) -> Result<Option<LockdownRecord>, BotError> {
+        let Some(lockdown) = self.get(guild_id).await? else {
+            return Ok(None);
+        };
+        let result = Entity::delete_by_id(lockdown.guild_id)
+            .exec(self.0.inner())
+            .await?;
+        Ok((result.rows_affected > 0).then_some(lockdown))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_one_lockdown_per_guild() {
+        for_each_backend(async |db| {
+            let repo = db.lockdown();
+            let guild = GuildId::new(1);
+            let lockdown = LockdownRecord {
+                guild_id: 1,
+                started_at: chrono::DateTime::from_timestamp(1_700_000_000, 0)
+                    .unwrap()
+                    .fixed_offset(),
+                started_by: Some(2),
+                verification: Some(1),
+                invites_paused: true,
+                slowmodes: "3:0,4:5".to_owned(),
+            };
+            assert_eq!(repo.get(guild).await.unwrap(), None);
+            assert!(repo.begin(lockdown.clone()).await.unwrap());
+            assert!(
+                !repo
+                    .begin(LockdownRecord {
+                        started_by: None,
+                        ..lockdown.clone()
+                    })
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(repo.get(guild).await.unwrap(), Some(lockdown.clone()));
+            assert_eq!(repo.get(GuildId::new(2)).await.unwrap(), None);
+
+            assert_eq!(repo.end(guild).await.unwrap(), Some(lockdown));
+            assert_eq!(repo.end(guild).await.unwrap(), None);
+            assert_eq!(repo.get(guild).await.unwrap(), None);
+        })
+        .await;
+    }
+}
@@ -1,12 +1,20 @@
-use chrono::{DateTime, FixedOffset};
-use entities::messages::*;
-use sea_orm::{QueryOrder, QuerySelect, Set, prelude::*, sea_query::*};
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use entities::{daily_activity, messages::*, reactions};
+use sea_orm::{
+    DatabaseTransaction, DbBackend, FromQueryResult, QueryOrder, QuerySelect, QueryTrait, Set,
+    TransactionTrait, prelude::*, sea_query::*,
+};
 use serenity::all::*;
 
 use crate::{database::BotDatabase, error::BotError};
 
 pub type MessageRecord = Model;
 
+/// Rows per `INSERT`, well below SQLite's bound parameter limit
+const CHUNK: usize = 1000;
+
 pub struct MsgRepo<'a>(&'a BotDatabase);
 impl BotDatabase {
     /// Get a reference to the database
@@ -15,6 +23,126 @@ impl BotDatabase {
     }
 }
 
+/// Column the statistics are grouped by
+#[derive(Debug, Clone, Copy)]
+enum By {
+    User,
+    Channel,
+}
+
+impl By {
+    fn raw(self) -> Column {
+        match self {
+            By::User => Column::UserId,
+            By::Channel => Column::ChannelId,
+        }
+    }
+
+    fn rollup(self) -> daily_activity::Column {
+        match self {
+            By::User => daily_activity::Column::UserId,
+            By::Channel => daily_activity::Column::ChannelId,
+        }
+    }
+}
+
+/// What the statistics add up
+#[derive(Debug, Clone, Copy)]
+enum Sum {
+    Messages,
+    Attachments,
+}
+
+impl Sum {
+    fn raw(self) -> SimpleExpr {
+        match self {
+            Sum::Messages => Column::MessageId.count(),
+            Sum::Attachments => Column::Attachments.sum(),
+        }
+    }
+
+    fn rollup(self) -> SimpleExpr {
+        match self {
+            Sum::Messages => daily_activity::Column::Count.sum(),
+            Sum::Attachments => daily_activity::Column::Attachments.sum(),
+        }
+    }
+}
+
+/// Key of a `daily_activity` row
+type ActivityKey = (i64, i64, i64, NaiveDate);
+
+/// Change of a `daily_activity` row's message and attachment counts
+type Delta = (i64, i64);
+
+fn activity_key(m: &Model) -> ActivityKey {
+    (
+        m.guild_id,
+        m.channel_id,
+        m.user_id,
+        m.timestamp.to_utc().date_naive(),
+    )
+}
+
+fn midnight(day: NaiveDate) -> DateTime<Utc> {
+    day.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Half-open time range
+type Range = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// `[from, to)` split into whole UTC days, answered by `daily_activity`, and the partial days
+/// at either edge, which have to be counted from `messages`
+#[derive(Debug, PartialEq, Eq)]
+struct Split {
+    days: Option<(Option<NaiveDate>, Option<NaiveDate>)>,
+    edges: Vec<Range>,
+}
+
+fn split(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Split {
+    let start = from.map(|f| match f == midnight(f.date_naive()) {
+        true => f.date_naive(),
+        false => f.date_naive() + chrono::Duration::days(1),
+    });
+    let end = to.map(|t| t.date_naive());
+    if let (Some(start), Some(end)) = (start, end)
+        && start >= end
+    {
+        return Split {
+            days: None,
+            edges: vec![(from, to)],
+        };
+    }
+    let mut edges = Vec::new();
+    if let (Some(from), Some(start)) = (from, start)
+        && from != midnight(start)
+    {
+        edges.push((Some(from), Some(midnight(start))));
+    }
+    if let (Some(to), Some(end)) = (to, end)
+        && to != midnight(end)
+    {
+        edges.push((Some(midnight(end)), Some(to)));
+    }
+    Split {
+        days: Some((start, end)),
+        edges,
+    }
+}
+
+fn sorted<T: From<u64>>(counts: HashMap<i64, i64>) -> Vec<(T, u64)> {
+    let mut counts = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, count)| (id as u64, count as u64))
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .map(|(id, count)| (T::from(id), count))
+        .collect()
+}
+
 impl MsgRepo<'_> {
     /// Record a message event
     pub async fn record(
@@ -25,53 +153,227 @@ impl MsgRepo<'_> {
         channel_id: ChannelId,
         timestamp: Timestamp,
     ) -> Result<(), BotError> {
-        let message = ActiveModel {
-            message_id: Set(message_id.get() as i64),
-            user_id: Set(user_id.get() as i64),
-            guild_id: Set(guild_id.get() as i64),
-            channel_id: Set(channel_id.get() as i64),
-            timestamp: Set(timestamp.to_utc().into()),
-        };
-        Entity::insert(message)
+        self.record_many(&[Model {
+            message_id: message_id.get() as i64,
+            user_id: user_id.get() as i64,
+            guild_id: guild_id.get() as i64,
+            channel_id: channel_id.get() as i64,
+            timestamp: timestamp.to_utc().into(),
+            edit_count: 0,
+            edited_at: None,
+            attachments: 0,
+            parent_id: None,
+        }])
+        .await?;
+        Ok(())
+    }
+
+    /// Record messages in one transaction, returning how many were new
+    ///
+    /// Already recorded messages are skipped so gateway replays don't count twice.
+    pub async fn record_many(&self, messages: &[MessageRecord]) -> Result<u64, BotError> {
+        let mut unique = HashMap::new();
+        for m in messages {
+            unique.entry(m.message_id).or_insert(m);
+        }
+        let unique = unique.into_values().collect::<Vec<_>>();
+        // Writing first takes SQLite's write lock up front, a read would have to be upgraded
+        // and fails instead of waiting when another writer got there first
+        let txn = self.0.inner().begin().await?;
+        let backend = txn.get_database_backend();
+        let mut inserted = HashSet::new();
+        for chunk in unique.chunks(CHUNK) {
+            let mut insert = Entity::insert_many(chunk.iter().map(|m| ActiveModel {
+                message_id: Set(m.message_id),
+                user_id: Set(m.user_id),
+                guild_id: Set(m.guild_id),
+                channel_id: Set(m.channel_id),
+                timestamp: Set(m.timestamp),
+                edit_count: Set(m.edit_count),
+                edited_at: Set(m.edited_at),
+                attachments: Set(m.attachments),
+                parent_id: Set(m.parent_id),
+            }))
             .on_conflict(
                 OnConflict::column(Column::MessageId)
                     .do_nothing()
                     .to_owned(),
             )
+            .into_query();
+            insert.returning_col(Column::MessageId);
+            for row in txn.query_all(backend.build(&insert)).await? {
+                inserted.insert(row.try_get_by_index::<i64>(0)?);
+            }
+        }
+        let mut activity = HashMap::<_, Delta>::new();
+        for m in unique.iter().filter(|m| inserted.contains(&m.message_id)) {
+            let delta = activity.entry(activity_key(m)).or_default();
+            delta.0 += 1;
+            delta.1 += m.attachments;
+        }
+        add_activity(&txn, activity).await?;
+        txn.commit().await?;
+        Ok(inserted.len() as u64)
+    }
+
+    /// Forget deleted messages and their reactions, returning how many were recorded
+    pub async fn delete(&self, message_ids: &[MessageId]) -> Result<u64, BotError> {
+        let txn = self.0.inner().begin().await?;
+        let backend = txn.get_database_backend();
+        let mut activity = HashMap::<_, Delta>::new();
+        let mut deleted = 0;
+        for chunk in message_ids.chunks(CHUNK) {
+            let ids = chunk.iter().map(|id| id.get() as i64).collect::<Vec<_>>();
+            let mut delete = Entity::delete_many()
+                .filter(Column::MessageId.is_in(ids.to_owned()))
+                .into_query();
+            delete.returning_all();
+            for row in txn.query_all(backend.build(&delete)).await? {
+                let m = Model::from_query_result(&row, "")?;
+                let delta = activity.entry(activity_key(&m)).or_default();
+                delta.0 -= 1;
+                delta.1 -= m.attachments;
+                deleted += 1;
+            }
+            reactions::Entity::delete_many()
+                .filter(reactions::Column::MessageId.is_in(ids))
+                .exec(&txn)
+                .await?;
+        }
+        let guilds = activity.keys().map(|k| k.0).collect::<Vec<_>>();
+        add_activity(&txn, activity).await?;
+        daily_activity::Entity::delete_many()
+            .filter(daily_activity::Column::GuildId.is_in(guilds))
+            .filter(daily_activity::Column::Count.lte(0))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Count an edit, returning whether the message was recorded
+    pub async fn record_edit(
+        &self,
+        message_id: MessageId,
+        edited_at: Timestamp,
+    ) -> Result<bool, BotError> {
+        let result = Entity::update_many()
+            .col_expr(Column::EditCount, Expr::col(Column::EditCount).add(1))
+            .col_expr(
+                Column::EditedAt,
+                Expr::value(Some(DateTime::<FixedOffset>::from(edited_at.to_utc()))),
+            )
+            .filter(Column::MessageId.eq(message_id.get() as i64))
             .exec(self.0.inner())
             .await?;
-        Ok(())
+        Ok(result.rows_affected > 0)
     }
 
-    /// Get channel statistics for a guild
-    pub async fn get_channel_stats(
+    /// Count messages from `messages`, used for partial days and to check the rollups
+    async fn count_raw(
         &self,
+        by: By,
+        sum: Sum,
         guild_id: GuildId,
-        from: Option<impl Into<DateTime<FixedOffset>>>,
-        to: Option<impl Into<DateTime<FixedOffset>>>,
-    ) -> Result<Vec<(ChannelId, u64)>, BotError> {
-        use sea_orm::sea_query::{Alias, Expr};
+        channel_ids: Option<&[ChannelId]>,
+        (from, to): Range,
+    ) -> Result<Vec<(i64, i64)>, BotError> {
+        Ok(Entity::find()
+            .select_only()
+            .column(by.raw())
+            .column_as(
+                Expr::expr(sum.raw()).cast_as(Alias::new("bigint")),
+                "message_count",
+            )
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .apply_if(channel_ids, |q, c| {
+                q.filter(Column::ChannelId.is_in(c.iter().map(|id| id.get() as i64)))
+            })
+            .apply_if(from, |q, f| {
+                q.filter(Column::Timestamp.gte(f.fixed_offset()))
+            })
+            .apply_if(to, |q, t| q.filter(Column::Timestamp.lt(t.fixed_offset())))
+            .group_by(by.raw())
+            .into_tuple()
+            .all(self.0.inner())
+            .await?)
+    }
+
+    /// Sum whole days from `daily_activity`
+    async fn count_rollup(
+        &self,
+        by: By,
+        sum: Sum,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        (start, end): (Option<NaiveDate>, Option<NaiveDate>),
+    ) -> Result<Vec<(i64, i64)>, BotError> {
+        use daily_activity::{Column, Entity};
 
-        const ALIAS: &str = "message_count";
         Ok(Entity::find()
             .select_only()
-            .column(Column::ChannelId)
+            .column(by.rollup())
+            .column_as(
+                Expr::expr(sum.rollup()).cast_as(Alias::new("bigint")),
+                "message_count",
+            )
             .filter(Column::GuildId.eq(guild_id.get() as i64))
-            .filter(from.map_or(SimpleExpr::Value(true.into()), |f| {
-                Column::Timestamp.gte(f.into())
-            }))
-            .filter(to.map_or(SimpleExpr::Value(true.into()), |t| {
-                Column::Timestamp.lt(t.into())
-            }))
-            .column_as(Column::MessageId.count(), ALIAS)
-            .group_by(Column::ChannelId)
-            .order_by_desc(Expr::col(Alias::new(ALIAS)))
-            .into_tuple::<(i64, i64)>()
+            .apply_if(channel_ids, |q, c| {
+                q.filter(Column::ChannelId.is_in(c.iter().map(|id| id.get() as i64)))
+            })
+            .apply_if(start, |q, s| q.filter(Column::Day.gte(s)))
+            .apply_if(end, |q, e| q.filter(Column::Day.lt(e)))
+            .group_by(by.rollup())
+            .into_tuple()
             .all(self.0.inner())
-            .await?
-            .into_iter()
-            .map(|(channel_id, count)| (ChannelId::new(channel_id as u64), count as u64))
-            .collect())
+            .await?)
+    }
+
+    async fn count(
+        &self,
+        by: By,
+        sum: Sum,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<DateTime<FixedOffset>>,
+        to: Option<DateTime<FixedOffset>>,
+    ) -> Result<HashMap<i64, i64>, BotError> {
+        let split = split(from.map(|f| f.to_utc()), to.map(|t| t.to_utc()));
+        let mut counts = HashMap::new();
+        if let Some(days) = split.days {
+            for (id, count) in self
+                .count_rollup(by, sum, guild_id, channel_ids, days)
+                .await?
+            {
+                *counts.entry(id).or_default() += count;
+            }
+        }
+        for edge in split.edges {
+            for (id, count) in self.count_raw(by, sum, guild_id, channel_ids, edge).await? {
+                *counts.entry(id).or_default() += count;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Get channel statistics for a guild
+    pub async fn get_channel_stats(
+        &self,
+        guild_id: GuildId,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(ChannelId, u64)>, BotError> {
+        let counts = self
+            .count(
+                By::Channel,
+                Sum::Messages,
+                guild_id,
+                None,
+                from.map(Into::into),
+                to.map(Into::into),
+            )
+            .await?;
+        Ok(sorted(counts))
     }
 
     /// Get user statistics for a guild
@@ -82,31 +384,83 @@ impl MsgRepo<'_> {
         from: Option<impl Into<DateTime<FixedOffset>>>,
         to: Option<impl Into<DateTime<FixedOffset>>>,
     ) -> Result<Vec<(UserId, u64)>, BotError> {
-        use sea_orm::sea_query::{Alias, Expr};
+        let counts = self
+            .count(
+                By::User,
+                Sum::Messages,
+                guild_id,
+                channel_ids,
+                from.map(Into::into),
+                to.map(Into::into),
+            )
+            .await?;
+        Ok(sorted(counts))
+    }
 
-        const ALIAS: &str = "message_count";
-        Ok(Entity::find()
+    /// Rank users of a guild by the number of attachments they posted
+    pub async fn get_attachment_stats(
+        &self,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(UserId, u64)>, BotError> {
+        let counts = self
+            .count(
+                By::User,
+                Sum::Attachments,
+                guild_id,
+                channel_ids,
+                from.map(Into::into),
+                to.map(Into::into),
+            )
+            .await?;
+        Ok(sorted(counts))
+    }
+
+    /// Get message counts of the threads and forum posts under `parent_id`
+    pub async fn get_thread_stats(
+        &self,
+        guild_id: GuildId,
+        parent_id: ChannelId,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(ChannelId, u64)>, BotError> {
+        let counts = Entity::find()
             .select_only()
-            .column(Column::UserId)
+            .column(Column::ChannelId)
+            .column_as(Column::MessageId.count(), "message_count")
             .filter(Column::GuildId.eq(guild_id.get() as i64))
-            .filter(channel_ids.map_or(SimpleExpr::Value(true.into()), |c| {
-                Column::ChannelId.is_in(c.iter().map(|id| id.get() as i64))
-            }))
-            .filter(from.map_or(SimpleExpr::Value(true.into()), |f| {
-                Column::Timestamp.gte(f.into())
-            }))
-            .filter(to.map_or(SimpleExpr::Value(true.into()), |t| {
-                Column::Timestamp.lt(t.into())
-            }))
-            .column_as(Column::MessageId.count(), ALIAS)
-            .group_by(Column::UserId)
-            .order_by_desc(Expr::col(Alias::new(ALIAS)))
+            .filter(Column::ParentId.eq(parent_id.get() as i64))
+            .apply_if(from, |q, f| {
+                q.filter(Column::Timestamp.gte(f.into().to_utc().fixed_offset()))
+            })
+            .apply_if(to, |q, t| {
+                q.filter(Column::Timestamp.lt(t.into().to_utc().fixed_offset()))
+            })
+            .group_by(Column::ChannelId)
             .into_tuple::<(i64, i64)>()
             .all(self.0.inner())
-            .await?
-            .into_iter()
-            .map(|(user_id, count)| (UserId::new(user_id as u64), count as u64))
-            .collect())
+            .await?;
+        Ok(sorted(counts.into_iter().collect()))
+    }
+
+    /// [`MsgRepo::get_user_stats`] counted from raw rows only, kept for benchmarks
+    pub async fn get_user_stats_raw(
+        &self,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(UserId, u64)>, BotError> {
+        let range = (
+            from.map(|f| f.into().to_utc()),
+            to.map(|t| t.into().to_utc()),
+        );
+        let counts = self
+            .count_raw(By::User, Sum::Messages, guild_id, channel_ids, range)
+            .await?;
+        Ok(sorted(counts.into_iter().collect()))
     }
 
     /// Get message records for a specific user in a guild
@@ -126,51 +480,389 @@ impl MsgRepo<'_> {
             .await?)
     }
 
+    /// Recompute `daily_activity` from `messages`
+    pub async fn rebuild_activity(&self) -> Result<(), BotError> {
+        let backend = self.0.inner().get_database_backend();
+        let day = match backend {
+            DbBackend::Postgres => "(timestamp AT TIME ZONE 'UTC')::date",
+            _ => "date(timestamp)",
+        };
+        let txn = self.0.inner().begin().await?;
+        daily_activity::Entity::delete_many().exec(&txn).await?;
+        txn.execute_unprepared(&format!(
+            "INSERT INTO daily_activity (guild_id, channel_id, user_id, day, count, attachments) \
+             SELECT guild_id, channel_id, user_id, {day}, COUNT(*), SUM(attachments) FROM \
+             messages GROUP BY guild_id, channel_id, user_id, {day}"
+        ))
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
     /// Clear all message data (dangerous operation)
     pub async fn nuke(&self) -> Result<(), BotError> {
-        Entity::delete_many().exec(self.0.inner()).await?;
+        let txn = self.0.inner().begin().await?;
+        Entity::delete_many().exec(&txn).await?;
+        daily_activity::Entity::delete_many().exec(&txn).await?;
+        reactions::Entity::delete_many().exec(&txn).await?;
+        txn.commit().await?;
         Ok(())
     }
 }
 
+/// Add `delta` to each day's counts, creating missing rows
+async fn add_activity(
+    txn: &DatabaseTransaction,
+    delta: HashMap<ActivityKey, Delta>,
+) -> Result<(), BotError> {
+    use daily_activity::{ActiveModel, Column, Entity};
+
+    let excluded = |column| Expr::col((Alias::new("excluded"), column));
+    let rows = delta
+        .into_iter()
+        .filter(|(_, delta)| *delta != (0, 0))
+        .collect::<Vec<_>>();
+    for chunk in rows.chunks(CHUNK) {
+        Entity::insert_many(chunk.iter().map(
+            |((guild, channel, user, day), (count, attachments))| ActiveModel {
+                guild_id: Set(*guild),
+                channel_id: Set(*channel),
+                user_id: Set(*user),
+                day: Set(*day),
+                count: Set(*count),
+                attachments: Set(*attachments),
+            },
+        ))
+        .on_conflict(
+            OnConflict::columns([
+                Column::GuildId,
+                Column::ChannelId,
+                Column::UserId,
+                Column::Day,
+            ])
+            .values([
+                (
+                    Column::Count,
+                    Expr::col((Entity, Column::Count)).add(excluded(Column::Count)),
+                ),
+                (
+                    Column::Attachments,
+                    Expr::col((Entity, Column::Attachments)).add(excluded(Column::Attachments)),
+                ),
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(txn)
+        .await?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
-    use migration::{Migrator, MigratorTrait, SchemaManager};
+    use sea_orm::Statement;
 
     use super::*;
-    use crate::database::BotDatabase;
+    use crate::{
+        config::DatabaseCfg,
+        database::{BotDatabase, for_each_backend},
+        utils::Zone,
+    };
 
     #[tokio::test]
     async fn test_record_message() {
-        let db = BotDatabase::new_memory().await.unwrap();
-        let migrations = Migrator::migrations();
-        let manager = SchemaManager::new(db.inner());
-        for migration in migrations {
-            migration.up(&manager).await.unwrap();
-        }
-        let service = db.message();
-        let message_id = MessageId::new(1);
-        let user_id = UserId::new(123);
-        let guild_id = GuildId::new(456);
-        let channel_id = ChannelId::new(789);
-        let timestamp = Timestamp::now();
-        service
-            .record(message_id, user_id, guild_id, channel_id, timestamp)
+        for_each_backend(async |db| {
+            let service = db.message();
+            let message_id = MessageId::new(1);
+            let user_id = UserId::new(123);
+            let guild_id = GuildId::new(456);
+            let channel_id = ChannelId::new(789);
+            let timestamp = Timestamp::now();
+            service
+                .record(message_id, user_id, guild_id, channel_id, timestamp)
+                .await
+                .unwrap();
+            // Gateway replays deliver the same message again
+            service
+                .record(message_id, user_id, guild_id, channel_id, timestamp)
+                .await
+                .unwrap();
+            let user_stats = service
+                .get_user_stats(
+                    guild_id,
+                    Some(&[channel_id]),
+                    None::<DateTime<FixedOffset>>,
+                    None::<DateTime<FixedOffset>>,
+                )
+                .await
+                .unwrap();
+            assert_eq!(user_stats.len(), 1);
+            assert_eq!(user_stats[0].0, user_id);
+            assert_eq!(user_stats[0].1, 1);
+            let channel_stats = service
+                .get_channel_stats(
+                    guild_id,
+                    None::<DateTime<FixedOffset>>,
+                    None::<DateTime<FixedOffset>>,
+                )
+                .await
+                .unwrap();
+            assert_eq!(channel_stats.len(), 1);
+            assert_eq!(channel_stats[0].0, channel_id);
+            assert_eq!(channel_stats[0].1, 1);
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_split_days() {
+        let at = |s: &str| Some(s.parse::<DateTime<Utc>>().unwrap());
+        let day = |s: &str| Some(s.parse::<NaiveDate>().unwrap());
+        assert_eq!(
+            split(at("2026-10-01T06:00:00Z"), at("2026-10-04T18:00:00Z")),
+            Split {
+                days: Some((day("2026-10-02"), day("2026-10-04"))),
+                edges: vec![
+                    (at("2026-10-01T06:00:00Z"), at("2026-10-02T00:00:00Z")),
+                    (at("2026-10-04T00:00:00Z"), at("2026-10-04T18:00:00Z")),
+                ],
+            }
+        );
+        assert_eq!(
+            split(at("2026-10-01T00:00:00Z"), None),
+            Split {
+                days: Some((day("2026-10-01"), None)),
+                edges: vec![],
+            }
+        );
+        // within a single day only raw rows are counted
+        assert_eq!(
+            split(at("2026-10-01T06:00:00Z"), at("2026-10-01T18:00:00Z")).days,
+            None
+        );
+        assert_eq!(
+            split(None, None),
+            Split {
+                days: Some((None, None)),
+                edges: vec![],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollups_match_raw_rows() {
+        for_each_backend(async |db| {
+            let guild_id = GuildId::new(1);
+            let start = "2026-10-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+            let mut seed = 42u64;
+            let mut next = |n: u64| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 33) % n
+            };
+            let messages = (1..=600)
+                .map(|id| MessageRecord {
+                    message_id: id,
+                    user_id: next(7) as i64 + 1,
+                    guild_id: next(2) as i64 + 1,
+                    channel_id: next(3) as i64 + 1,
+                    timestamp: (start + chrono::Duration::minutes(next(10 * 24 * 60) as i64))
+                        .into(),
+                    edit_count: 0,
+                    edited_at: None,
+                    attachments: next(3) as i64,
+                    parent_id: None,
+                })
+                .collect::<Vec<_>>();
+            let repo = db.message();
+            assert_eq!(repo.record_many(&messages).await.unwrap(), 600);
+            // replays and duplicates within a batch are skipped
+            assert_eq!(repo.record_many(&messages[..50]).await.unwrap(), 0);
+            let deleted = (1..=600).step_by(7).map(MessageId::new).collect::<Vec<_>>();
+            assert_eq!(repo.delete(&deleted).await.unwrap(), 86);
+
+            let shanghai = FixedOffset::east_opt(8 * 3600).unwrap();
+            let at = |s: &str| Some(s.parse::<DateTime<FixedOffset>>().unwrap());
+            for (from, to) in [
+                (None, None),
+                (at("2026-10-02T07:30:00Z"), at("2026-10-08T13:15:00Z")),
+                (at("2026-10-03T00:00:00Z"), at("2026-10-05T00:00:00Z")),
+                (at("2026-10-04T01:00:00Z"), at("2026-10-04T23:00:00Z")),
+                (at("2026-10-06T00:00:00+08:00"), None),
+                (
+                    None,
+                    Some(start.with_timezone(&shanghai) + chrono::Duration::days(3)),
+                ),
+                // local calendar days as `/stats` asks for them
+                {
+                    let (from, to) = Zone::Iana(chrono_tz::Asia::Shanghai)
+                        .date_window("2026-10-03".parse().unwrap(), "2026-10-05".parse().unwrap());
+                    (Some(from), Some(to))
+                },
+            ] {
+                for channels in [None, Some(&[ChannelId::new(2)][..])] {
+                    assert_eq!(
+                        repo.get_user_stats(guild_id, channels, from, to)
+                            .await
+                            .unwrap(),
+                        repo.get_user_stats_raw(guild_id, channels, from, to)
+                            .await
+                            .unwrap(),
+                        "{from:?}..{to:?} in {channels:?}"
+                    );
+                }
+            }
+            let none = None::<DateTime<FixedOffset>>;
+            let mut attachments = HashMap::<i64, i64>::new();
+            for m in &messages {
+                if m.guild_id == 1 && (m.message_id - 1) % 7 != 0 {
+                    *attachments.entry(m.user_id).or_default() += m.attachments;
+                }
+            }
+            assert_eq!(
+                repo.get_attachment_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap(),
+                sorted::<UserId>(attachments)
+            );
+            let before = repo
+                .get_user_stats(guild_id, None, none, none)
+                .await
+                .unwrap();
+            repo.rebuild_activity().await.unwrap();
+            assert_eq!(
+                repo.get_user_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap(),
+                before
+            );
+
+            let mut total = 0;
+            for guild_id in [guild_id, GuildId::new(2)] {
+                let channels = repo.get_channel_stats(guild_id, none, none).await.unwrap();
+                total += channels.iter().map(|(_, count)| count).sum::<u64>();
+            }
+            assert_eq!(total, messages.len() as u64 - 86);
+
+            repo.nuke().await.unwrap();
+            assert!(
+                daily_activity::Entity::find()
+                    .all(db.inner())
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_edits_and_threads() {
+        for_each_backend(async |db| {
+            let repo = db.message();
+            let message = MessageRecord {
+                message_id: 1,
+                user_id: 2,
+                guild_id: 3,
+                channel_id: 4,
+                timestamp: Utc::now().into(),
+                edit_count: 0,
+                edited_at: None,
+                attachments: 2,
+                parent_id: Some(5),
+            };
+            repo.record_many(&[message]).await.unwrap();
+            let edited_at = Timestamp::now();
+            for _ in 0..2 {
+                assert!(
+                    repo.record_edit(MessageId::new(1), edited_at)
+                        .await
+                        .unwrap()
+                );
+            }
+            assert!(
+                !repo
+                    .record_edit(MessageId::new(9), edited_at)
+                    .await
+                    .unwrap()
+            );
+            let stored = repo
+                .get_user_messages(UserId::new(2), GuildId::new(3))
+                .await
+                .unwrap();
+            assert_eq!(stored[0].edit_count, 2);
+            assert_eq!(
+                stored[0].edited_at.map(|t| t.timestamp()),
+                Some(edited_at.unix_timestamp())
+            );
+
+            let none = None::<DateTime<FixedOffset>>;
+            assert_eq!(
+                repo.get_thread_stats(GuildId::new(3), ChannelId::new(5), none, none)
+                    .await
+                    .unwrap(),
+                vec![(ChannelId::new(4), 1)]
+            );
+            assert_eq!(
+                repo.get_attachment_stats(GuildId::new(3), None, none, none)
+                    .await
+                    .unwrap(),
+                vec![(UserId::new(2), 2)]
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_record_load() {
+        const TASKS: u64 = 32;
+        const PER_TASK: u64 = 100;
+        let dir = std::env::temp_dir().join(format!("dog-bot-load-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = BotDatabase::new(dir.join("load.db"), &DatabaseCfg::default())
             .await
             .unwrap();
-        let user_stats = service
-            .get_user_stats(
-                guild_id,
-                Some(&[channel_id]),
-                None::<DateTime<FixedOffset>>,
-                None::<DateTime<FixedOffset>>,
-            )
+        db.migrate().await.unwrap();
+        let journal = db
+            .inner()
+            .query_one(Statement::from_string(
+                db.inner().get_database_backend(),
+                "PRAGMA journal_mode",
+            ))
             .await
+            .unwrap()
+            .unwrap()
+            .try_get_by_index::<String>(0)
             .unwrap();
-        assert_eq!(user_stats.len(), 1);
-        assert_eq!(user_stats[0].0, user_id);
-        assert_eq!(user_stats[0].1, 1);
-        let channel_stats = service
+        assert_eq!(journal, "wal");
+
+        let guild_id = GuildId::new(1);
+        let tasks = (0..TASKS)
+            .map(|task| {
+                let db = db.to_owned();
+                tokio::spawn(async move {
+                    for i in 0..PER_TASK {
+                        db.message()
+                            .record(
+                                MessageId::new(task * PER_TASK + i + 1),
+                                UserId::new(task + 1),
+                                guild_id,
+                                ChannelId::new(i % 4 + 1),
+                                Timestamp::now(),
+                            )
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let channels = db
+            .message()
             .get_channel_stats(
                 guild_id,
                 None::<DateTime<FixedOffset>>,
@@ -178,8 +870,10 @@ mod test {
             )
             .await
             .unwrap();
-        assert_eq!(channel_stats.len(), 1);
-        assert_eq!(channel_stats[0].0, channel_id);
-        assert_eq!(channel_stats[0].1, 1);
+        assert_eq!(
+            channels.iter().map(|(_, count)| count).sum::<u64>(),
+            TASKS * PER_TASK
+        );
+        std::fs::remove_dir_all(dir).ok();
     }
 }
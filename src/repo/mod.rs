@@ -1,2 +1,15 @@
 // mod flush;
-// mod messages;
+mod automod;
+mod cases;
+mod lockdowns;
+mod messages;
+mod reactions;
+mod samples;
+mod timezones;
+mod usage;
+
+pub use automod::RuleRecord;
+#[cfg(test)]
+pub(crate) use automod::test_rule;
+pub use cases::{Case, CaseAction};
+pub use lockdowns::LockdownRecord;
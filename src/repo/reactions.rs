@@ -0,0 +1,264 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use entities::{messages, reactions::*};
+use sea_orm::{
+    QuerySelect, QueryTrait, Set,
+    prelude::*,
+    sea_query::{Expr, OnConflict},
+};
+use serenity::all::*;
+
+use crate::{database::BotDatabase, error::BotError};
+
+pub struct ReactionRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn reaction(&self) -> ReactionRepo<'_> {
+        ReactionRepo(self)
+    }
+}
+
+impl ReactionRepo<'_> {
+    /// Record a reaction, looking up the author among recorded messages when Discord omits it
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        emoji: &str,
+        author_id: Option<UserId>,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        reacted_at: DateTime<Utc>,
+    ) -> Result<(), BotError> {
+        let author_id = match author_id {
+            Some(author_id) => Some(author_id.get() as i64),
+            None => messages::Entity::find_by_id(message_id.get() as i64)
+                .one(self.0.inner())
+                .await?
+                .map(|m| m.user_id),
+        };
+        let reaction = ActiveModel {
+            message_id: Set(message_id.get() as i64),
+            user_id: Set(user_id.get() as i64),
+            emoji: Set(emoji.to_owned()),
+            author_id: Set(author_id),
+            guild_id: Set(guild_id.get() as i64),
+            channel_id: Set(channel_id.get() as i64),
+            reacted_at: Set(reacted_at.into()),
+        };
+        Entity::insert(reaction)
+            .on_conflict(
+                OnConflict::columns([Column::MessageId, Column::UserId, Column::Emoji])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(self.0.inner())
+            .await?;
+        Ok(())
+    }
+
+    /// Forget a removed reaction
+    pub async fn remove(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        emoji: &str,
+    ) -> Result<(), BotError> {
+        Entity::delete_by_id((
+            message_id.get() as i64,
+            user_id.get() as i64,
+            emoji.to_owned(),
+        ))
+        .exec(self.0.inner())
+        .await?;
+        Ok(())
+    }
+
+    /// Forget every reaction on a message, or only those with `emoji`
+    pub async fn clear(&self, message_id: MessageId, emoji: Option<&str>) -> Result<(), BotError> {
+        Entity::delete_many()
+            .filter(Column::MessageId.eq(message_id.get() as i64))
+            .apply_if(emoji, |q, e| q.filter(Column::Emoji.eq(e)))
+            .exec(self.0.inner())
+            .await?;
+        Ok(())
+    }
+
+    async fn rank(
+        &self,
+        by: Column,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<DateTime<FixedOffset>>,
+        to: Option<DateTime<FixedOffset>>,
+    ) -> Result<Vec<(UserId, u64)>, BotError> {
+        let mut counts = Entity::find()
+            .select_only()
+            .column(by)
+            .column_as(Column::MessageId.count(), "reaction_count")
+            .filter(Column::GuildId.eq(guild_id.get() as i64))
+            .filter(by.is_not_null())
+            // reacting to your own message doesn't count as receiving one
+            .filter(
+                Expr::col(Column::AuthorId)
+                    .ne(Expr::col(Column::UserId))
+                    .or(Column::AuthorId.is_null()),
+            )
+            .apply_if(channel_ids, |q, c| {
+                q.filter(Column::ChannelId.is_in(c.iter().map(|id| id.get() as i64)))
+            })
+            .apply_if(from, |q, f| {
+                q.filter(Column::ReactedAt.gte(f.to_utc().fixed_offset()))
+            })
+            .apply_if(to, |q, t| {
+                q.filter(Column::ReactedAt.lt(t.to_utc().fixed_offset()))
+            })
+            .group_by(by)
+            .into_tuple::<(i64, i64)>()
+            .all(self.0.inner())
+            .await?;
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(counts
+            .into_iter()
+            .map(|(user_id, count)| (UserId::new(user_id as u64), count as u64))
+            .collect())
+    }
+
+    /// Rank users of a guild by reactions others added to their messages
+    pub async fn get_received_stats(
+        &self,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(UserId, u64)>, BotError> {
+        self.rank(
+            Column::AuthorId,
+            guild_id,
+            channel_ids,
+            from.map(Into::into),
+            to.map(Into::into),
+        )
+        .await
+    }
+
+    /// Rank users of a guild by reactions they added to others' messages
+    pub async fn get_given_stats(
+        &self,
+        guild_id: GuildId,
+        channel_ids: Option<&[ChannelId]>,
+        from: Option<impl Into<DateTime<FixedOffset>>>,
+        to: Option<impl Into<DateTime<FixedOffset>>>,
+    ) -> Result<Vec<(UserId, u64)>, BotError> {
+        self.rank(
+            Column::UserId,
+            guild_id,
+            channel_ids,
+            from.map(Into::into),
+            to.map(Into::into),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_reaction_rankings() {
+        for_each_backend(async |db| {
+            let guild_id = GuildId::new(1);
+            let channel_id = ChannelId::new(2);
+            let (alice, bob, carol) = (UserId::new(10), UserId::new(11), UserId::new(12));
+            db.message()
+                .record(
+                    MessageId::new(100),
+                    alice,
+                    guild_id,
+                    channel_id,
+                    Timestamp::now(),
+                )
+                .await
+                .unwrap();
+            let repo = db.reaction();
+            let now = Utc::now();
+            // the author is looked up from the recorded message when Discord omits it
+            for (user, emoji) in [(bob, "👍"), (bob, "🎉"), (carol, "👍"), (alice, "👍")] {
+                repo.add(
+                    MessageId::new(100),
+                    user,
+                    emoji,
+                    None,
+                    guild_id,
+                    channel_id,
+                    now,
+                )
+                .await
+                .unwrap();
+            }
+            repo.add(
+                MessageId::new(200),
+                alice,
+                "1234",
+                Some(bob),
+                guild_id,
+                channel_id,
+                now,
+            )
+            .await
+            .unwrap();
+            repo.add(
+                MessageId::new(200),
+                alice,
+                "1234",
+                Some(bob),
+                guild_id,
+                channel_id,
+                now,
+            )
+            .await
+            .unwrap();
+
+            let none = None::<DateTime<FixedOffset>>;
+            assert_eq!(
+                repo.get_received_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap(),
+                vec![(alice, 3), (bob, 1)]
+            );
+            assert_eq!(
+                repo.get_given_stats(guild_id, Some(&[channel_id]), none, none)
+                    .await
+                    .unwrap(),
+                vec![(bob, 2), (alice, 1), (carol, 1)]
+            );
+
+            repo.remove(MessageId::new(100), bob, "🎉").await.unwrap();
+            repo.clear(MessageId::new(100), Some("🎉")).await.unwrap();
+            assert_eq!(
+                repo.get_given_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap(),
+                vec![(alice, 1), (bob, 1), (carol, 1)]
+            );
+            // deleting a message drops its reactions
+            db.message().delete(&[MessageId::new(100)]).await.unwrap();
+            assert_eq!(
+                repo.get_received_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap(),
+                vec![(bob, 1)]
+            );
+            repo.clear(MessageId::new(200), None).await.unwrap();
+            assert!(
+                repo.get_given_stats(guild_id, None, none, none)
+                    .await
+                    .unwrap()
+                    .is_empty()
+            );
+        })
+        .await;
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration, Utc};
+use entities::system_samples::*;
+use sea_orm::{QueryOrder, Set, prelude::*};
+
+use crate::{database::BotDatabase, error::BotError, sampler::Sample};
+
+pub struct SampleRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn sample(&self) -> SampleRepo<'_> {
+        SampleRepo(self)
+    }
+}
+
+impl From<Model> for Sample {
+    fn from(m: Model) -> Self {
+        Sample {
+            at: m.sampled_at.to_utc(),
+            cpu_usage: m.cpu_usage as f32,
+            used_memory: m.used_memory as u64,
+            total_memory: m.total_memory as u64,
+            allocated: m.allocated as u64,
+            resident: m.resident as u64,
+            gateway_latency: m
+                .gateway_latency_ms
+                .map(|ms| std::time::Duration::from_millis(ms as u64)),
+            db_latency: std::time::Duration::from_micros(m.db_latency_us as u64),
+            alive_tasks: m.alive_tasks as usize,
+            queue_depth: m.queue_depth as usize,
+        }
+    }
+}
+
+impl SampleRepo<'_> {
+    /// Persist a system sample
+    pub async fn insert(&self, sample: &Sample) -> Result<(), BotError> {
+        let model = ActiveModel {
+            sampled_at: Set(sample.at.into()),
+            cpu_usage: Set(sample.cpu_usage as f64),
+            used_memory: Set(sample.used_memory as i64),
+            total_memory: Set(sample.total_memory as i64),
+            allocated: Set(sample.allocated as i64),
+            resident: Set(sample.resident as i64),
+            gateway_latency_ms: Set(sample.gateway_latency.map(|d| d.as_millis() as i64)),
+            db_latency_us: Set(sample.db_latency.as_micros() as i64),
+            alive_tasks: Set(sample.alive_tasks as i64),
+            queue_depth: Set(sample.queue_depth as i64),
+            ..Default::default()
+        };
+        model.insert(self.0.inner()).await?;
+        Ok(())
+    }
+
+    /// Get samples taken since the given time, oldest first
+    pub async fn since(&self, from: DateTime<Utc>) -> Result<Vec<Sample>, BotError> {
+        Ok(Entity::find()
+            .filter(Column::SampledAt.gte(from))
+            .order_by_asc(Column::SampledAt)
+            .all(self.0.inner())
+            .await?
+            .into_iter()
+            .map(Sample::from)
+            .collect())
+    }
+
+    /// Clean up old samples
+    pub async fn clean(&self, dur: Duration) -> Result<(), BotError> {
+        Entity::delete_many()
+            .filter(Column::SampledAt.lt(Utc::now() - dur))
+            .exec(self.0.inner())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_insert_and_clean_samples() {
+        for_each_backend(async |db| {
+            let repo = db.sample();
+            let sample = |at: DateTime<Utc>| Sample {
+                at,
+                cpu_usage: 12.5,
+                used_memory: 1024,
+                total_memory: 4096,
+                allocated: 256,
+                resident: 512,
+                gateway_latency: Some(std::time::Duration::from_millis(42)),
+                db_latency: std::time::Duration::from_micros(100),
+                alive_tasks: 7,
+                queue_depth: 0,
+            };
+            repo.insert(&sample(Utc::now() - Duration::days(2)))
+                .await
+                .unwrap();
+            repo.insert(&sample(Utc::now())).await.unwrap();
+
+            let samples = repo.since(Utc::now() - Duration::days(3)).await.unwrap();
+            assert_eq!(samples.len(), 2);
+            assert_eq!(samples[1].cpu_usage, 12.5);
+            assert_eq!(
+                samples[1].gateway_latency,
+                Some(std::time::Duration::from_millis(42))
+            );
+
+            repo.clean(Duration::days(1)).await.unwrap();
+            let samples = repo.since(Utc::now() - Duration::days(3)).await.unwrap();
+            assert_eq!(samples.len(), 1);
+        })
+        .await;
+    }
+}
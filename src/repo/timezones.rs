@@ -0,0 +1,132 @@
+use chrono_tz::Tz;
+use entities::timezones::*;
+use sea_orm::{Set, prelude::*, sea_query::OnConflict};
+use serenity::all::*;
+
+use crate::{database::BotDatabase, error::BotError};
+
+const USER: &str = "user";
+const GUILD: &str = "guild";
+
+pub struct TimezoneRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn timezone(&self) -> TimezoneRepo<'_> {
+        TimezoneRepo(self)
+    }
+}
+
+impl TimezoneRepo<'_> {
+    async fn set(&self, scope: &str, target_id: u64, tz: Tz) -> Result<(), BotError> {
+        let model = ActiveModel {
+            scope: Set(scope.to_owned()),
+            target_id: Set(target_id as i64),
+            timezone: Set(tz.name().to_owned()),
+        };
+        Entity::insert(model)
+            .on_conflict(
+                OnConflict::columns([Column::Scope, Column::TargetId])
+                    .update_column(Column::Timezone)
+                    .to_owned(),
+            )
+            .exec(self.0.inner())
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, scope: &str, target_id: u64) -> Result<Option<Tz>, BotError> {
+        Ok(Entity::find_by_id((scope.to_owned(), target_id as i64))
+            .one(self.0.inner())
+            .await?
+            .and_then(|m| m.timezone.parse().ok()))
+    }
+
+    async fn clear(&self, scope: &str, target_id: u64) -> Result<(), BotError> {
+        Entity::delete_by_id((scope.to_owned(), target_id as i64))
+            .exec(self.0.inner())
+            .await?;
+        Ok(())
+    }
+
+    /// Set a user's preferred time zone
+    pub async fn set_user(&self, user_id: UserId, tz: Tz) -> Result<(), BotError> {
+        self.set(USER, user_id.get(), tz).await
+    }
+
+    /// Set a guild's default time zone
+    pub async fn set_guild(&self, guild_id: GuildId, tz: Tz) -> Result<(), BotError> {
+        self.set(GUILD, guild_id.get(), tz).await
+    }
+
+    /// Remove a user's preferred time zone
+    pub async fn clear_user(&self, user_id: UserId) -> Result<(), BotError> {
+        self.clear(USER, user_id.get()).await
+    }
+
+    /// Remove a guild's default time zone
+    pub async fn clear_guild(&self, guild_id: GuildId) -> Result<(), BotError> {
+        self.clear(GUILD, guild_id.get()).await
+    }
+
+    pub async fn get_user(&self, user_id: UserId) -> Result<Option<Tz>, BotError> {
+        self.get(USER, user_id.get()).await
+    }
+
+    pub async fn get_guild(&self, guild_id: GuildId) -> Result<Option<Tz>, BotError> {
+        self.get(GUILD, guild_id.get()).await
+    }
+
+    /// The user's zone, falling back to the guild's
+    pub async fn resolve(
+        &self,
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+    ) -> Result<Option<Tz>, BotError> {
+        if let Some(tz) = self.get_user(user_id).await? {
+            return Ok(Some(tz));
+        }
+        match guild_id {
+            Some(guild_id) => self.get_guild(guild_id).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_resolve_timezone() {
+        for_each_backend(async |db| {
+            let repo = db.timezone();
+            let user_id = UserId::new(1);
+            let guild_id = GuildId::new(2);
+            assert_eq!(repo.resolve(user_id, Some(guild_id)).await.unwrap(), None);
+
+            repo.set_guild(guild_id, chrono_tz::Asia::Shanghai)
+                .await
+                .unwrap();
+            assert_eq!(
+                repo.resolve(user_id, Some(guild_id)).await.unwrap(),
+                Some(chrono_tz::Asia::Shanghai)
+            );
+
+            repo.set_user(user_id, chrono_tz::Europe::Berlin)
+                .await
+                .unwrap();
+            repo.set_user(user_id, chrono_tz::America::New_York)
+                .await
+                .unwrap();
+            assert_eq!(
+                repo.resolve(user_id, Some(guild_id)).await.unwrap(),
+                Some(chrono_tz::America::New_York)
+            );
+
+            repo.clear_user(user_id).await.unwrap();
+            assert_eq!(repo.resolve(user_id, None).await.unwrap(), None);
+        })
+        .await;
+    }
+}
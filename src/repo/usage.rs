@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use entities::command_usage::*;
+use sea_orm::{QueryOrder, QuerySelect, Set, prelude::*, sea_query::SimpleExpr};
+use serenity::all::*;
+
+use crate::{database::BotDatabase, error::BotError};
+
+/// Aggregated usage of a single command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandStats {
+    pub command: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+pub struct UsageRepo<'a>(&'a BotDatabase);
+impl BotDatabase {
+    /// Get a reference to the database
+    pub fn usage(&self) -> UsageRepo<'_> {
+        UsageRepo(self)
+    }
+}
+
+/// 1-based nearest rank of percentile `p` among `len` sorted values
+fn rank(len: u64, p: f64) -> u64 {
+    ((p * len as f64).ceil() as u64).clamp(1, len.max(1))
+}
+
+impl UsageRepo<'_> {
+    /// Record a finished command invocation
+    pub async fn record(
+        &self,
+        command: &str,
+        guild_id: Option<GuildId>,
+        channel_id: ChannelId,
+        user_id: UserId,
+        duration: Duration,
+        error_kind: Option<&str>,
+    ) -> Result<(), BotError> {
+        let usage = ActiveModel {
+            command: Set(command.to_owned()),
+            guild_id: Set(guild_id.map(|id| id.get() as i64)),
+            channel_id: Set(channel_id.get() as i64),
+            user_id: Set(user_id.get() as i64),
+            invoked_at: Set((Utc::now() - duration).into()),
+            duration_ms: Set(duration.as_millis() as i64),
+            error_kind: Set(error_kind.map(str::to_owned)),
+            ..Default::default()
+        };
+        usage.insert(self.0.inner()).await?;
+        Ok(())
+    }
+
+    /// Per-command call counts, error counts and latency percentiles, most used first
+    pub async fn stats(&self, from: Option<DateTime<Utc>>) -> Result<Vec<CommandStats>, BotError> {
+        let since = || from.map_or(SimpleExpr::Value(true.into()), |f| Column::InvokedAt.gte(f));
+        let counts = Entity::find()
+            .select_only()
+            .column(Column::Command)
+            .column_as(Column::Id.count(), "calls")
+            .column_as(Column::ErrorKind.count(), "errors")
+            .filter(since())
+            .group_by(Column::Command)
+            .into_tuple::<(String, i64, i64)>()
+            .all(self.0.inner())
+            .await?;
+
+        let mut stats = Vec::with_capacity(counts.len());
+        for (command, calls, errors) in counts {
+            let calls = calls as u64;
+            // Only the two ranked rows are read, never every duration of the command
+            let at = async |p| -> Result<Duration, BotError> {
+                let ms = Entity::find()
+                    .select_only()
+                    .column(Column::DurationMs)
+                    .filter(since())
+                    .filter(Column::Command.eq(&command))
+                    .order_by_asc(Column::DurationMs)
+                    .offset(rank(calls, p) - 1)
+                    .limit(1)
+                    .into_tuple::<i64>()
+                    .one(self.0.inner())
+                    .await?;
+                Ok(Duration::from_millis(ms.unwrap_or_default() as u64))
+            };
+            let p50 = at(0.5).await?;
+            let p95 = at(0.95).await?;
+            stats.push(CommandStats {
+                command,
+                calls,
+                errors: errors as u64,
+                p50,
+                p95,
+            });
+        }
+        stats.sort_by(|a, b| {
+            b.calls
+                .cmp(&a.calls)
+                .then_with(|| a.command.cmp(&b.command))
+        });
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::for_each_backend;
+
+    #[tokio::test]
+    async fn test_command_stats() {
+        for_each_backend(async |db| {
+            let repo = db.usage();
+            let guild_id = Some(GuildId::new(1));
+            let channel_id = ChannelId::new(2);
+            let user_id = UserId::new(3);
+            for ms in 1..=20 {
+                repo.record(
+                    "system_info",
+                    guild_id,
+                    channel_id,
+                    user_id,
+                    Duration::from_millis(ms * 10),
+                    (ms % 10 == 0).then_some("database"),
+                )
+                .await
+                .unwrap();
+            }
+            repo.record(
+                "debug memory",
+                None,
+                channel_id,
+                user_id,
+                Duration::ZERO,
+                None,
+            )
+            .await
+            .unwrap();
+
+            let stats = repo.stats(None).await.unwrap();
+            assert_eq!(stats.len(), 2);
+            assert_eq!(
+                stats[0],
+                CommandStats {
+                    command: "system_info".to_string(),
+                    calls: 20,
+                    errors: 2,
+                    p50: Duration::from_millis(100),
+                    p95: Duration::from_millis(190),
+                }
+            );
+            assert_eq!(stats[1].calls, 1);
+
+            let future = repo
+                .stats(Some(Utc::now() + chrono::Duration::hours(1)))
+                .await
+                .unwrap();
+            assert!(future.is_empty());
+        })
+        .await;
+    }
+}
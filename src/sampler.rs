@@ -0,0 +1,260 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serenity::all::ShardManager;
+use sysinfo::System;
+use tokio::time::MissedTickBehavior;
+use tracing::warn;
+
+use crate::{database::BotDatabase, error::BotError};
+
+/// How often the sampler takes a snapshot
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// How long samples are kept, both in memory and in the database
+pub const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A point-in-time snapshot of the bot and host
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub at: DateTime<Utc>,
+    pub cpu_usage: f32,
+    pub used_memory: u64,
+    pub total_memory: u64,
+    pub allocated: u64,
+    pub resident: u64,
+    pub gateway_latency: Option<Duration>,
+    pub db_latency: Duration,
+    pub alive_tasks: usize,
+    pub queue_depth: usize,
+}
+
+/// Min, average and max of a value over a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+impl Sample {
+    async fn take(
+        sys: &mut System,
+        shard_manager: &ShardManager,
+        db: &BotDatabase,
+    ) -> Result<Self, BotError> {
+        use tikv_jemalloc_ctl::{epoch, stats};
+        sys.refresh_cpu_usage();
+        sys.refresh_memory();
+        epoch::advance()?;
+        let latencies = shard_manager
+            .runners
+            .lock()
+            .await
+            .values()
+            .filter_map(|r| r.latency)
+            .collect::<Vec<_>>();
+        let metrics = tokio::runtime::Handle::current().metrics();
+        Ok(Sample {
+            at: Utc::now(),
+            cpu_usage: sys.global_cpu_usage(),
+            used_memory: sys.used_memory(),
+            total_memory: sys.total_memory(),
+            allocated: stats::allocated::read()? as u64,
+            resident: stats::resident::read()? as u64,
+            gateway_latency: (!latencies.is_empty())
+                .then(|| latencies.iter().sum::<Duration>() / latencies.len() as u32),
+            db_latency: db.latency().await?,
+            alive_tasks: metrics.num_alive_tasks(),
+            queue_depth: metrics.global_queue_depth(),
+        })
+    }
+}
+
+/// Ring buffer of recent samples filled by a background task
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    samples: Arc<RwLock<VecDeque<Sample>>>,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        let capacity = (RETENTION.as_secs() / SAMPLE_INTERVAL.as_secs()) as usize;
+        Self {
+            samples: Arc::new(RwLock::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    fn push(&self, sample: Sample) {
+        let mut samples = self.samples.write().expect("sampler lock poisoned");
+        samples.push_back(sample);
+        let bound = Utc::now() - RETENTION;
+        while samples.front().is_some_and(|s| s.at < bound) {
+            samples.pop_front();
+        }
+    }
+
+    /// The most recent sample, if any has been taken yet
+    pub fn latest(&self) -> Option<Sample> {
+        self.samples
+            .read()
+            .expect("sampler lock poisoned")
+            .back()
+            .cloned()
+    }
+
+    /// Samples taken within the last `window`, oldest first
+    pub fn window(&self, window: Duration) -> Vec<Sample> {
+        let bound = Utc::now() - window;
+        self.samples
+            .read()
+            .expect("sampler lock poisoned")
+            .iter()
+            .filter(|s| s.at >= bound)
+            .cloned()
+            .collect()
+    }
+
+    /// Min/avg/max of `f` over the last `window`
+    pub fn summary(&self, window: Duration, f: impl Fn(&Sample) -> Option<f64>) -> Option<Summary> {
+        let values = self.window(window).iter().filter_map(f).collect::<Vec<_>>();
+        summarize(&values)
+    }
+
+    /// `f` over the last `window`, averaged into at most `buckets` points
+    pub fn series(
+        &self,
+        window: Duration,
+        buckets: usize,
+        f: impl Fn(&Sample) -> Option<f64>,
+    ) -> Vec<f64> {
+        let values = self.window(window).iter().filter_map(f).collect::<Vec<_>>();
+        if values.is_empty() || buckets == 0 {
+            return values;
+        }
+        values
+            .chunks(values.len().div_ceil(buckets))
+            .map(|c| c.iter().sum::<f64>() / c.len() as f64)
+            .collect()
+    }
+
+    /// Reload persisted samples and start sampling in the background
+    pub async fn spawn(self, shard_manager: Arc<ShardManager>, db: BotDatabase, persist: bool) {
+        if persist {
+            match db.sample().since(Utc::now() - RETENTION).await {
+                Ok(samples) => samples.into_iter().for_each(|s| self.push(s)),
+                Err(e) => warn!("Failed to load persisted samples: {}", e),
+            }
+        }
+
+        tokio::spawn(async move {
+            let mut sys = System::new();
+            // CPU usage is computed against the previous refresh
+            sys.refresh_cpu_usage();
+            tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                let sample = match Sample::take(&mut sys, &shard_manager, &db).await {
+                    Ok(sample) => sample,
+                    Err(e) => {
+                        warn!("Failed to take system sample: {}", e);
+                        continue;
+                    }
+                };
+                if persist {
+                    let repo = db.sample();
+                    if let Err(e) = repo.insert(&sample).await {
+                        warn!("Failed to persist system sample: {}", e);
+                    }
+                    if let Err(e) = repo
+                        .clean(chrono::Duration::from_std(RETENTION).unwrap())
+                        .await
+                    {
+                        warn!("Failed to clean old system samples: {}", e);
+                    }
+                }
+                self.push(sample);
+            }
+        });
+    }
+}
+
+fn summarize(values: &[f64]) -> Option<Summary> {
+    if values.is_empty() {
+        return None;
+    }
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    Some(Summary {
+        min,
+        avg: values.iter().sum::<f64>() / values.len() as f64,
+        max,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(minutes_ago: i64, cpu_usage: f32) -> Sample {
+        Sample {
+            at: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            cpu_usage,
+            used_memory: 0,
+            total_memory: 0,
+            allocated: 0,
+            resident: 0,
+            gateway_latency: None,
+            db_latency: Duration::ZERO,
+            alive_tasks: 0,
+            queue_depth: 0,
+        }
+    }
+
+    #[test]
+    fn test_summary_and_retention() {
+        let sampler = Sampler::new();
+        sampler.push(sample(25 * 60, 99.0));
+        sampler.push(sample(90, 50.0));
+        sampler.push(sample(30, 10.0));
+        sampler.push(sample(0, 20.0));
+
+        // the 25h old sample is evicted by the push after it
+        assert_eq!(sampler.window(RETENTION).len(), 3);
+        let hour = sampler
+            .summary(Duration::from_secs(3600), |s| Some(s.cpu_usage as f64))
+            .unwrap();
+        assert_eq!(
+            hour,
+            Summary {
+                min: 10.0,
+                avg: 15.0,
+                max: 20.0
+            }
+        );
+        let day = sampler
+            .summary(RETENTION, |s| Some(s.cpu_usage as f64))
+            .unwrap();
+        assert_eq!(day.max, 50.0);
+        assert_eq!(
+            sampler.series(RETENTION, 2, |s| Some(s.cpu_usage as f64)),
+            vec![30.0, 20.0]
+        );
+        assert_eq!(sampler.latest().unwrap().cpu_usage, 20.0);
+    }
+}
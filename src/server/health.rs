@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use serenity::gateway::ConnectionStage;
+
+use super::ServerState;
+
+const RUNTIME_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Outcome of a single probe
+#[derive(Serialize, Debug)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Liveness {
+    pub status: &'static str,
+    pub runtime: Check,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub status: &'static str,
+    pub gateway: Check,
+    pub cache: Check,
+    pub database: Check,
+}
+
+fn status(ok: bool) -> (StatusCode, &'static str) {
+    if ok {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    }
+}
+
+/// Liveness: the process answers and the runtime still schedules tasks
+pub async fn healthz() -> (StatusCode, Json<Liveness>) {
+    let start = std::time::Instant::now();
+    let runtime = match tokio::time::timeout(RUNTIME_TIMEOUT, tokio::spawn(async {})).await {
+        Ok(Ok(())) => Check {
+            ok: true,
+            detail: format!("task scheduled in {} µs", start.elapsed().as_micros()),
+        },
+        Ok(Err(e)) => Check {
+            ok: false,
+            detail: format!("task failed: {e}"),
+        },
+        Err(_) => Check {
+            ok: false,
+            detail: format!(
+                "task not scheduled within {} ms",
+                RUNTIME_TIMEOUT.as_millis()
+            ),
+        },
+    };
+    let (code, status) = status(runtime.ok);
+    (code, Json(Liveness { status, runtime }))
+}
+
+/// Readiness: every shard is connected, READY arrived, the cache has all guilds and the
+/// database answers
+pub async fn readyz(State(state): State<ServerState>) -> (StatusCode, Json<Readiness>) {
+    let gateway = {
+        let runners = state.shard_manager.runners.lock().await;
+        let connected = runners
+            .values()
+            .filter(|r| r.stage == ConnectionStage::Connected)
+            .count();
+        Check {
+            ok: !runners.is_empty() && connected == runners.len(),
+            detail: format!("{connected}/{} shards connected", runners.len()),
+        }
+    };
+
+    // Nothing is unavailable before READY either, because nothing is known yet
+    let cache = if !state.ready.get() {
+        Check {
+            ok: false,
+            detail: "READY not received".to_string(),
+        }
+    } else {
+        let unavailable = state.cache.unavailable_guilds().len();
+        Check {
+            ok: unavailable == 0,
+            detail: format!(
+                "{} guilds cached, {unavailable} unavailable",
+                state.cache.guild_count()
+            ),
+        }
+    };
+
+    let database = match state.db.latency().await {
+        Ok(latency) => Check {
+            ok: true,
+            detail: format!("responded in {} ms", latency.as_millis()),
+        },
+        Err(e) => Check {
+            ok: false,
+            detail: e.to_string(),
+        },
+    };
+
+    let (code, status) = status(gateway.ok && cache.ok && database.ok);
+    (
+        code,
+        Json(Readiness {
+            status,
+            gateway,
+            cache,
+            database,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use serenity::{
+        all::{GatewayIntents, Http, ShardManager, ShardManagerOptions},
+        cache::Cache,
+        prelude::{Mutex, RwLock, TypeMap},
+    };
+
+    use super::*;
+    use crate::{database::BotDatabase, server::GatewayReady};
+
+    async fn spawn_server(ready: bool) -> SocketAddr {
+        let cache = Arc::new(Cache::new());
+        let (shard_manager, _) = ShardManager::new(ShardManagerOptions {
+            data: Arc::new(RwLock::new(TypeMap::new())),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            framework: Arc::default(),
+            shard_index: 0,
+            shard_init: 0,
+            shard_total: 1,
+            ws_url: Arc::new(Mutex::new(String::new())),
+            cache: cache.to_owned(),
+            http: Arc::new(Http::new("")),
+            intents: GatewayIntents::empty(),
+            presence: None,
+        });
+        let state = ServerState {
+            cache,
+            shard_manager,
+            db: BotDatabase::new_memory().await.unwrap(),
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            ready: GatewayReady::default(),
+        };
+        if ready {
+            state.ready.set();
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, super::super::router(state)).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_healthz() {
+        let addr = spawn_server(false).await;
+        let res = reqwest::get(format!("http://{addr}/healthz"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let body: serenity::json::Value = res.json().await.unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["runtime"]["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_without_shards() {
+        let addr = spawn_server(true).await;
+        let res = reqwest::get(format!("http://{addr}/readyz")).await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serenity::json::Value = res.json().await.unwrap();
+        assert_eq!(body["status"], "unavailable");
+        assert_eq!(body["gateway"]["ok"], false);
+        assert_eq!(body["cache"]["ok"], true);
+        assert_eq!(body["database"]["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_before_ready() {
+        let addr = spawn_server(false).await;
+        let body: serenity::json::Value = reqwest::get(format!("http://{addr}/readyz"))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        // An empty cache has no unavailable guilds, READY decides
+        assert_eq!(body["cache"]["ok"], false);
+        assert_eq!(body["cache"]["detail"], "READY not received");
+    }
+}
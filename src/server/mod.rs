@@ -0,0 +1,71 @@
+mod health;
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use axum::{
+    Router, extract::State, http::header::CONTENT_TYPE, response::IntoResponse, routing::get,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serenity::{all::ShardManager, cache::Cache, prelude::TypeMapKey};
+use tracing::{info, warn};
+
+use crate::{database::BotDatabase, error::BotError};
+
+/// Shared state handed to every HTTP handler
+#[derive(Clone)]
+pub struct ServerState {
+    pub cache: Arc<Cache>,
+    pub shard_manager: Arc<ShardManager>,
+    pub db: BotDatabase,
+    pub metrics: PrometheusHandle,
+    pub ready: GatewayReady,
+}
+
+/// Set once the gateway sent READY, the cache is empty but complete-looking before that
+#[derive(Debug, Clone, Default)]
+pub struct GatewayReady(Arc<AtomicBool>);
+
+impl TypeMapKey for GatewayReady {
+    type Value = GatewayReady;
+}
+
+impl GatewayReady {
+    pub fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .with_state(state)
+}
+
+/// Serve the HTTP endpoints until the listener fails
+pub async fn serve(addr: SocketAddr, state: ServerState) -> Result<(), BotError> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("HTTP server listening on {}", addr);
+    Ok(axum::serve(listener, router(state)).await?)
+}
+
+async fn metrics(State(state): State<ServerState>) -> impl IntoResponse {
+    if let Err(e) = crate::metrics::collect(&state.cache, &state.shard_manager, &state.db).await {
+        warn!("Failed to collect metrics: {}", e);
+    }
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
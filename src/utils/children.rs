@@ -1,28 +1,176 @@
+use std::collections::HashMap;
+
 use serenity::all::*;
 
-fn get_direct_children_channels(guild: &Guild, channel: &GuildChannel) -> Vec<GuildChannel> {
-    guild
-        .channels
-        .values()
-        .filter(|&c| c.parent_id == Some(channel.id))
+/// A channel with the channels, threads and forum posts nested under it
+#[derive(Debug, Clone)]
+pub struct ChannelNode<'a> {
+    pub channel: &'a GuildChannel,
+    pub children: Vec<ChannelNode<'a>>,
+}
+
+impl<'a> ChannelNode<'a> {
+    /// This channel and every descendant with their depth, in display order
+    pub fn walk(&self) -> Vec<(usize, &'a GuildChannel)> {
+        fn visit<'a>(
+            node: &ChannelNode<'a>,
+            depth: usize,
+            out: &mut Vec<(usize, &'a GuildChannel)>,
+        ) {
+            out.push((depth, node.channel));
+            for child in &node.children {
+                visit(child, depth + 1, out);
+            }
+        }
+        let mut out = Vec::new();
+        visit(self, 0, &mut out);
+        out
+    }
+
+    /// This channel and every descendant
+    pub fn channels(&self) -> Vec<&'a GuildChannel> {
+        self.walk().into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+/// Order Discord shows channels in: text-like before voice, then by position
+fn display_order(c: &GuildChannel) -> (bool, bool, u16, ChannelId) {
+    let voice = matches!(c.kind, ChannelType::Voice | ChannelType::Stage);
+    (c.kind == ChannelType::Category, voice, c.position, c.id)
+}
+
+/// Parent → children index over a guild's channels and active threads, built once
+#[derive(Debug)]
+pub struct ChannelTree<'a> {
+    children: HashMap<Option<ChannelId>, Vec<&'a GuildChannel>>,
+}
+
+impl<'a> ChannelTree<'a> {
+    pub fn new(guild: &'a Guild) -> Self {
+        let mut children = HashMap::<_, Vec<_>>::new();
+        for channel in guild.channels.values().chain(&guild.threads) {
+            children.entry(channel.parent_id).or_default().push(channel);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|c| display_order(c));
+        }
+        Self { children }
+    }
+
+    /// Direct children of `parent`, threads and forum posts included
+    pub fn children(&self, parent: ChannelId) -> &[&'a GuildChannel] {
+        self.children.get(&Some(parent)).map_or(&[], Vec::as_slice)
+    }
+
+    /// `channel` with everything nested under it
+    pub fn subtree(&self, channel: &'a GuildChannel) -> ChannelNode<'a> {
+        ChannelNode {
+            channel,
+            children: self
+                .children(channel.id)
+                .iter()
+                .map(|c| self.subtree(c))
+                .collect(),
+        }
+    }
+
+    /// Uncategorized channels followed by the categories, as Discord lists them
+    pub fn roots(&self) -> Vec<ChannelNode<'a>> {
+        self.children
+            .get(&None)
+            .into_iter()
+            .flatten()
+            .map(|c| self.subtree(c))
+            .collect()
+    }
+}
+
+/// `channel` and every channel, thread and forum post nested under it
+pub fn get_all_children_channels(guild: &Guild, channel: &GuildChannel) -> Vec<GuildChannel> {
+    ChannelTree::new(guild)
+        .subtree(channel)
+        .channels()
+        .into_iter()
         .cloned()
         .collect()
 }
 
-pub fn get_all_children_channels(guild: &Guild, channel: &GuildChannel) -> Vec<GuildChannel> {
-    let mut channels = vec![vec![channel.to_owned()]];
+/// Channel named `c{id}` for the tests, everything else is left at its default
+#[cfg(test)]
+pub(crate) fn test_channel(
+    id: u64,
+    kind: ChannelType,
+    parent: Option<u64>,
+    position: u16,
+) -> GuildChannel {
+    let mut channel = GuildChannel::default();
+    channel.id = ChannelId::new(id);
+    channel.kind = kind;
+    channel.parent_id = parent.map(ChannelId::new);
+    channel.position = position;
+    channel.name = format!("c{id}");
+    channel
+}
 
-    loop {
-        let children: Vec<GuildChannel> = channels
-            .last()
-            .unwrap()
-            .iter()
-            .flat_map(|c| get_direct_children_channels(guild, c))
-            .collect();
-        if children.is_empty() {
-            break;
+#[cfg(test)]
+mod test {
+    use super::{test_channel as channel, *};
+
+    /// Two categories with text, voice and forum channels, threads and forum posts
+    fn guild() -> Guild {
+        let mut guild = Guild::default();
+        for c in [
+            channel(1, ChannelType::Category, None, 1),
+            channel(2, ChannelType::Category, None, 0),
+            channel(3, ChannelType::Voice, Some(1), 0),
+            channel(4, ChannelType::Text, Some(1), 1),
+            channel(5, ChannelType::Forum, Some(2), 0),
+            channel(6, ChannelType::Text, None, 5),
+        ] {
+            guild.channels.insert(c.id, c);
         }
-        channels.push(children);
+        guild.threads = vec![
+            channel(11, ChannelType::PublicThread, Some(4), 0),
+            channel(10, ChannelType::PublicThread, Some(4), 0),
+            channel(12, ChannelType::PublicThread, Some(5), 0),
+        ];
+        guild
+    }
+
+    fn ids(channels: &[(usize, &GuildChannel)]) -> Vec<(usize, u64)> {
+        channels.iter().map(|(d, c)| (*d, c.id.get())).collect()
+    }
+
+    #[test]
+    fn test_tree_includes_threads_and_forum_posts() {
+        let guild = guild();
+        let tree = ChannelTree::new(&guild);
+        let walked = tree
+            .roots()
+            .iter()
+            .flat_map(|root| root.walk())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids(&walked),
+            vec![
+                (0, 6),
+                (0, 2),
+                (1, 5),
+                (2, 12),
+                (0, 1),
+                (1, 4),
+                (2, 10),
+                (2, 11),
+                (1, 3),
+            ]
+        );
+
+        let category = &guild.channels[&ChannelId::new(1)];
+        let flat = get_all_children_channels(&guild, category)
+            .iter()
+            .map(|c| c.id.get())
+            .collect::<Vec<_>>();
+        assert_eq!(flat, vec![1, 4, 10, 11, 3]);
+        assert!(tree.children(ChannelId::new(3)).is_empty());
     }
-    channels.into_iter().flatten().collect()
 }
@@ -1,3 +1,11 @@
 mod children;
+mod sparkline;
+mod time;
 
-pub use children::get_all_children_channels;
+#[cfg(test)]
+pub(crate) use children::test_channel;
+pub use children::{ChannelNode, ChannelTree, get_all_children_channels};
+pub use sparkline::sparkline;
+pub use time::Zone;
+#[cfg(test)]
+pub(crate) use time::test_snowflake;
@@ -0,0 +1,122 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The time zone used to render times and to cut date windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    /// An IANA zone that follows daylight saving changes
+    Iana(Tz),
+    /// A fixed UTC offset
+    Fixed(FixedOffset),
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Zone::Iana(Tz::UTC)
+    }
+}
+
+impl std::fmt::Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Zone::Iana(tz) => write!(f, "{}", tz.name()),
+            Zone::Fixed(offset) => write!(f, "UTC{offset}"),
+        }
+    }
+}
+
+impl Zone {
+    /// Convert a UTC instant to local time in this zone
+    pub fn localize(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Zone::Iana(tz) => time.with_timezone(tz).fixed_offset(),
+            Zone::Fixed(offset) => time.with_timezone(offset),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.localize(Utc::now())
+    }
+
+    /// Format a UTC instant with the zone abbreviation, e.g. `CST` or `+08:00`
+    pub fn format(&self, time: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            Zone::Iana(tz) => time.with_timezone(tz).format(fmt).to_string(),
+            Zone::Fixed(offset) => time.with_timezone(offset).format(fmt).to_string(),
+        }
+    }
+
+    /// First instant of `date` in this zone
+    ///
+    /// When midnight is skipped by a DST transition the day starts at the first valid instant.
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<FixedOffset> {
+        fn first_valid<T: TimeZone>(zone: &T, date: NaiveDate) -> DateTime<FixedOffset> {
+            (0..24)
+                .filter_map(|h| {
+                    zone.from_local_datetime(&date.and_time(NaiveTime::from_hms_opt(h, 0, 0)?))
+                        .earliest()
+                })
+                .next()
+                .expect("every day has a valid hour")
+                .fixed_offset()
+        }
+        match self {
+            Zone::Iana(tz) => first_valid(tz, date),
+            Zone::Fixed(offset) => first_valid(offset, date),
+        }
+    }
+
+    /// Half-open `[start, end)` bounds covering the local dates `from..=to`
+    pub fn date_window(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+        let end = to.succ_opt().unwrap_or(to);
+        (self.start_of_day(from), self.start_of_day(end))
+    }
+
+    /// Today's date in this zone
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+/// Raw snowflake of something created `secs_ago` seconds before `now`
+#[cfg(test)]
+pub(crate) fn test_snowflake(now: serenity::all::Timestamp, secs_ago: i64) -> u64 {
+    /// Start of 2015, the Discord epoch, in milliseconds
+    const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+    let ms = (now.unix_timestamp() - secs_ago) as u64 * 1000 - DISCORD_EPOCH_MS;
+    ms << 22
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_date_window_follows_dst() {
+        let zone = Zone::Iana(chrono_tz::America::New_York);
+        // spring forward: the day is 23 hours long
+        let day = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let (start, end) = zone.date_window(day, day);
+        assert_eq!(start.offset().local_minus_utc(), -5 * 3600);
+        assert_eq!(end.offset().local_minus_utc(), -4 * 3600);
+        assert_eq!((end - start).num_hours(), 23);
+
+        let fixed = Zone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap());
+        let (start, end) = fixed.date_window(day, day);
+        assert_eq!(start.to_utc().to_rfc3339(), "2024-03-09T16:00:00+00:00");
+        assert_eq!((end - start).num_hours(), 24);
+    }
+
+    #[test]
+    fn test_skipped_midnight() {
+        // Chile skips 00:00 -> 01:00 when DST starts
+        let zone = Zone::Iana(chrono_tz::America::Santiago);
+        let day = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        let start = zone.start_of_day(day);
+        assert_eq!(start.format("%H:%M").to_string(), "01:00");
+    }
+}